@@ -0,0 +1,117 @@
+//! POSIX ACL decoding.
+//!
+//! Linux stores access control lists in the `system.posix_acl_access` and
+//! `system.posix_acl_default` extended attributes. Each holds a 4-byte version
+//! header followed by 8-byte entries: tag (u16), permissions (u16) and a user
+//! or group id (u32), all little-endian.
+
+use std::ffi::OsStr;
+
+/// Attribute holding the ACL that controls access to the file itself
+pub const ACCESS: &str = "system.posix_acl_access";
+/// Attribute holding the ACL inherited by new files in a directory
+pub const DEFAULT: &str = "system.posix_acl_default";
+
+/// The only ACL format version Linux writes
+const VERSION: u32 = 2;
+/// Size of one encoded entry in bytes
+const ENTRY_SIZE: usize = 8;
+
+/// Who an ACL entry applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tag {
+    UserObj,    // The file's owner
+    User(u32),  // A named user
+    GroupObj,   // The file's group
+    Group(u32), // A named group
+    Mask,       // Upper bound for named entries and the group
+    Other,      // Everyone else
+}
+
+/// One decoded ACL entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AclEntry {
+    pub tag: Tag,  // Who the entry applies to
+    pub perms: u8, // Permission bits: 4 read, 2 write, 1 execute
+}
+
+impl AclEntry {
+    /// Renders the permissions as "rwx", with '-' for missing bits
+    pub fn perms_string(&self) -> String {
+        [(4, 'r'), (2, 'w'), (1, 'x')]
+            .iter()
+            .map(|&(bit, c)| if self.perms & bit == 0 { '-' } else { c })
+            .collect()
+    }
+}
+
+/// Returns true if an attribute name is one of the POSIX ACL attributes
+pub fn is_acl_attribute(name: &OsStr) -> bool {
+    name == ACCESS || name == DEFAULT
+}
+
+/// Decodes a binary ACL attribute value.
+/// Returns None if the value is truncated or has an unknown version or tag.
+pub fn parse(value: &[u8]) -> Option<Vec<AclEntry>> {
+    let (header, body) = value.split_first_chunk::<4>()?;
+    if u32::from_le_bytes(*header) != VERSION || body.len() % ENTRY_SIZE != 0 {
+        return None;
+    }
+
+    body.chunks_exact(ENTRY_SIZE)
+        .map(|entry| {
+            let tag = u16::from_le_bytes([entry[0], entry[1]]);
+            let perms = u16::from_le_bytes([entry[2], entry[3]]);
+            let id = u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]);
+            let tag = match tag {
+                0x01 => Tag::UserObj,
+                0x02 => Tag::User(id),
+                0x04 => Tag::GroupObj,
+                0x08 => Tag::Group(id),
+                0x10 => Tag::Mask,
+                0x20 => Tag::Other,
+                _ => return None,
+            };
+            Some(AclEntry {
+                tag,
+                perms: u8::try_from(perms & 0o7).ok()?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(entries: &[(u16, u16, u32)]) -> Vec<u8> {
+        let mut value = VERSION.to_le_bytes().to_vec();
+        for &(tag, perms, id) in entries {
+            value.extend(tag.to_le_bytes());
+            value.extend(perms.to_le_bytes());
+            value.extend(id.to_le_bytes());
+        }
+        value
+    }
+
+    #[test]
+    fn test_parse_acl() {
+        let value = encode(&[
+            (0x01, 6, u32::MAX),
+            (0x02, 7, 1000),
+            (0x04, 4, u32::MAX),
+            (0x10, 7, u32::MAX),
+            (0x20, 0, u32::MAX),
+        ]);
+        let entries = parse(&value).unwrap();
+        assert_eq!(entries.len(), 5);
+        assert_eq!(entries[1].tag, Tag::User(1000));
+        assert_eq!(entries[0].perms_string(), "rw-");
+        assert_eq!(entries[1].perms_string(), "rwx");
+        assert_eq!(entries[4].perms_string(), "---");
+
+        assert_eq!(parse(&value[..value.len() - 1]), None);
+        assert_eq!(parse(&encode(&[(0x40, 7, 0)])), None);
+        assert_eq!(parse(&[1, 0, 0, 0]), None);
+    }
+}
@@ -0,0 +1,210 @@
+//! File name colours keyed like GNU `LS_COLORS`.
+//!
+//! A colour is looked up by its two-letter key; so far only `ca`, for files
+//! with capabilities, is. An entry for that key in `LS_COLORS` (e.g.
+//! `ca=30;41`) overrides the built-in colour. SGR codes are limited to what `colored` can express:
+//! bold, dim, italic, underline, blink, reverse and 16, 256 or 24-bit colours.
+
+use colored::{Color, ColoredString, Colorize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Text attributes and colours decoded from an SGR sequence
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    fg: Option<Color>, // Foreground colour
+    bg: Option<Color>, // Background colour
+    bold: bool,        // 1
+    dimmed: bool,      // 2
+    italic: bool,      // 3
+    underline: bool,   // 4
+    blink: bool,       // 5
+    reversed: bool,    // 7
+}
+
+impl Style {
+    /// Decodes an SGR sequence such as "01;34" or "38;5;208".
+    /// Returns None for codes that can't be represented.
+    pub fn parse(sgr: &str) -> Option<Style> {
+        let codes = sgr
+            .split(';')
+            .map(|code| {
+                if code.is_empty() {
+                    Ok(0)
+                } else {
+                    code.parse::<u8>()
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        let mut style = Style::default();
+        let mut codes = codes.into_iter();
+        while let Some(code) = codes.next() {
+            match code {
+                0 => style = Style::default(),
+                1 => style.bold = true,
+                2 => style.dimmed = true,
+                3 => style.italic = true,
+                4 => style.underline = true,
+                5 => style.blink = true,
+                7 => style.reversed = true,
+                30..=37 => style.fg = Some(basic_color(code - 30, false)),
+                90..=97 => style.fg = Some(basic_color(code - 90, true)),
+                40..=47 => style.bg = Some(basic_color(code - 40, false)),
+                100..=107 => style.bg = Some(basic_color(code - 100, true)),
+                38 | 48 => {
+                    let color = extended_color(&mut codes)?;
+                    if code == 38 {
+                        style.fg = Some(color);
+                    } else {
+                        style.bg = Some(color);
+                    }
+                }
+                _ => return None,
+            }
+        }
+        Some(style)
+    }
+
+    /// Applies the style to text
+    pub fn paint(&self, text: &str) -> ColoredString {
+        let mut painted = text.normal();
+        if let Some(fg) = self.fg {
+            painted = painted.color(fg);
+        }
+        if let Some(bg) = self.bg {
+            painted = painted.on_color(bg);
+        }
+        if self.bold {
+            painted = painted.bold();
+        }
+        if self.dimmed {
+            painted = painted.dimmed();
+        }
+        if self.italic {
+            painted = painted.italic();
+        }
+        if self.underline {
+            painted = painted.underline();
+        }
+        if self.blink {
+            painted = painted.blink();
+        }
+        if self.reversed {
+            painted = painted.reversed();
+        }
+        painted
+    }
+}
+
+/// One of the eight standard colours, or its bright variant
+fn basic_color(index: u8, bright: bool) -> Color {
+    const COLORS: [(Color, Color); 8] = [
+        (Color::Black, Color::BrightBlack),
+        (Color::Red, Color::BrightRed),
+        (Color::Green, Color::BrightGreen),
+        (Color::Yellow, Color::BrightYellow),
+        (Color::Blue, Color::BrightBlue),
+        (Color::Magenta, Color::BrightMagenta),
+        (Color::Cyan, Color::BrightCyan),
+        (Color::White, Color::BrightWhite),
+    ];
+    let (normal, light) = COLORS[usize::from(index)];
+    if bright {
+        light
+    } else {
+        normal
+    }
+}
+
+/// Decodes the rest of a 38/48 sequence: "5;N" (256 colours) or "2;R;G;B"
+fn extended_color(codes: &mut impl Iterator<Item = u8>) -> Option<Color> {
+    match codes.next()? {
+        5 => Some(match codes.next()? {
+            n @ 0..=7 => basic_color(n, false),
+            n @ 8..=15 => basic_color(n - 8, true),
+            n @ 16..=231 => {
+                // 6x6x6 colour cube
+                let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+                let n = n - 16;
+                Color::TrueColor {
+                    r: level(n / 36),
+                    g: level(n / 6 % 6),
+                    b: level(n % 6),
+                }
+            }
+            n => {
+                // Grayscale ramp
+                let gray = 8 + (n - 232) * 10;
+                Color::TrueColor {
+                    r: gray,
+                    g: gray,
+                    b: gray,
+                }
+            }
+        }),
+        2 => Some(Color::TrueColor {
+            r: codes.next()?,
+            g: codes.next()?,
+            b: codes.next()?,
+        }),
+        _ => None,
+    }
+}
+
+/// Styles by key, parsed from an `LS_COLORS` value. Extension patterns
+/// (`*.rs=...`) and entries that can't be decoded are ignored.
+fn parse_ls_colors(value: &str) -> HashMap<String, Style> {
+    value
+        .split(':')
+        .filter_map(|entry| {
+            let (key, sgr) = entry.split_once('=')?;
+            if key.starts_with('*') {
+                return None;
+            }
+            Some((key.to_string(), Style::parse(sgr)?))
+        })
+        .collect()
+}
+
+/// Colours `text` with the `LS_COLORS` style for `key`, or returns None
+/// when the key isn't set there and the built-in colour applies
+pub fn paint(key: &str, text: &str) -> Option<ColoredString> {
+    static OVERRIDES: OnceLock<HashMap<String, Style>> = OnceLock::new();
+    let overrides = OVERRIDES.get_or_init(|| {
+        std::env::var("LS_COLORS")
+            .map(|value| parse_ls_colors(&value))
+            .unwrap_or_default()
+    });
+    overrides.get(key).map(|style| style.paint(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_style() {
+        let style = Style::parse("01;34").unwrap();
+        assert_eq!(style.fg, Some(Color::Blue));
+        assert!(style.bold);
+        let style = Style::parse("30;41").unwrap();
+        assert_eq!((style.fg, style.bg), (Some(Color::Black), Some(Color::Red)));
+        let style = Style::parse("38;5;208").unwrap();
+        assert_eq!(
+            style.fg,
+            Some(Color::TrueColor {
+                r: 255,
+                g: 135,
+                b: 0
+            })
+        );
+        assert_eq!(Style::parse("0"), Some(Style::default()));
+        assert_eq!(Style::parse("38;5"), None);
+        assert_eq!(Style::parse("zz"), None);
+
+        let keys = parse_ls_colors("di=01;34:*.rs=32:ca=bad:ex=31");
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys["ex"].fg, Some(Color::Red));
+    }
+}
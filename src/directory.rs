@@ -4,44 +4,121 @@
 //! and managing recursive directory traversal.
 
 use crate::file_info::FileInfo;
-use std::fs;
+use crate::filter::Filter;
+use crate::output::report_unreadable;
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
 use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
 use std::path::Path;
+use std::rc::Rc;
+
+/// Apparent and on-disk size of a directory tree (--total-size)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TreeSize {
+    pub apparent: u64,  // Sum of file lengths
+    pub allocated: u64, // Bytes allocated on disk
+}
+
+impl TreeSize {
+    /// The size of one file or directory on its own
+    fn of(metadata: &Metadata) -> Self {
+        TreeSize {
+            apparent: metadata.len(),
+            allocated: metadata.blocks() * 512,
+        }
+    }
+
+    /// Adds another size to this one
+    fn add(&mut self, other: TreeSize) {
+        self.apparent += other.apparent;
+        self.allocated += other.allocated;
+    }
+}
+
+/// Order in which recursive listings visit directories (--walk)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum WalkOrder {
+    #[default]
+    Dfs, // Each directory's subdirectories before its siblings (like GNU ls -R)
+    Bfs, // All directories at one depth before any deeper ones
+}
+
+/// Reads a directory lazily, yielding each entry as it is read.
+/// Skips hidden files unless show_all is set; nothing is filtered or sorted.
+pub fn read_entries(
+    dir: &Path,
+    show_all: bool,
+) -> io::Result<impl Iterator<Item = io::Result<FileInfo>>> {
+    Ok(fs::read_dir(dir)?.filter_map(move |entry| match entry {
+        // Skip hidden files unless -a flag is set
+        Ok(entry) if !show_all && entry.file_name().as_bytes().starts_with(b".") => None,
+        Ok(entry) => Some(FileInfo::from_entry(entry)),
+        Err(e) => Some(Err(e)),
+    }))
+}
 
 /// Reads a directory and collects file information.
-/// Filters hidden files based on the show_all flag.
+/// Filters hidden files based on the show_all flag, and drops entries
+/// that fail `filter` (--type, --size, ...). `root` is the path being
+/// listed, which --full-path patterns are relative to.
 /// Continues processing even if some entries fail to read.
-pub fn collect_entries(dir: &Path, show_all: bool) -> io::Result<Vec<FileInfo>> {
+pub fn collect_entries(
+    dir: &Path,
+    show_all: bool,
+    filter: &Filter,
+    root: &Path,
+) -> io::Result<Vec<FileInfo>> {
     let mut entries = vec![];
+    let mut dropped = vec![];
     let mut errors = vec![];
 
-    for entry in fs::read_dir(dir)? {
+    for entry in read_entries(dir, show_all)? {
         match entry {
-            Ok(entry) => {
-                let file_name = entry.file_name().to_string_lossy().to_string();
-
-                // Skip hidden files unless -a flag is set
-                if !show_all && file_name.starts_with('.') {
-                    continue;
-                }
-
-                match FileInfo::from_entry(entry) {
-                    Ok(file_info) => entries.push(file_info),
-                    Err(e) => errors.push(e),
-                }
-            }
+            Ok(file_info) if filter.matches(&file_info, root) => entries.push(file_info),
+            Ok(file_info) => dropped.push(file_info),
             Err(e) => errors.push(e),
         }
     }
 
-    // Report errors but continue processing
+    // Report errors but continue processing. Entries left out by the
+    // filter are never listed, so report the ones it couldn't examine here.
     for error in errors {
         eprintln!("ls: {error}");
     }
+    report_unreadable(&dropped);
 
     Ok(entries)
 }
 
+/// Collects every entry below `root` for a flat listing (--flat), each
+/// renamed to its path relative to `root`. Entries that fail `filter` are
+/// left out, but directories are descended into either way. Errors reading
+/// subdirectories are reported and skipped.
+pub fn collect_tree(root: &Path, show_all: bool, filter: &Filter) -> io::Result<Vec<FileInfo>> {
+    let mut found = vec![];
+    let mut pending = vec![collect_entries(root, show_all, &Filter::default(), root)?];
+
+    while let Some(entries) = pending.pop() {
+        for mut entry in entries {
+            if entry.is_dir() && !entry.is_hidden() {
+                match collect_entries(&entry.path, show_all, &Filter::default(), root) {
+                    Ok(children) => pending.push(children),
+                    Err(e) => eprintln!("ls: {}: {e}", entry.path.display()),
+                }
+            }
+            if filter.matches(&entry, root) {
+                entry.relative_to(root);
+                found.push(entry);
+            } else {
+                report_unreadable(std::slice::from_ref(&entry));
+            }
+        }
+    }
+    Ok(found)
+}
+
 /// Filters directory entries for recursive traversal.
 /// Returns only non-hidden directories from the given entries.
 pub fn get_subdirectories(entries: &[FileInfo]) -> Vec<&FileInfo> {
@@ -50,3 +127,98 @@ pub fn get_subdirectories(entries: &[FileInfo]) -> Vec<&FileInfo> {
         .filter(|f| f.is_dir() && !f.is_hidden())
         .collect()
 }
+
+/// Whole-tree sizes of directories (--total-size). Each directory is
+/// walked once and remembered by (device, inode), so the listings of a
+/// recursive run reuse the sizes of subtrees their ancestors already walked.
+#[derive(Default)]
+pub struct TreeSizes {
+    trees: HashMap<(u64, u64), Rc<Tree>>, // Walked directories
+}
+
+/// What a walked directory tree holds. Files with several hard links are
+/// kept by inode, so each is counted once in any tree holding its links.
+#[derive(Default)]
+struct Tree {
+    single: TreeSize,                      // Directories and files with one link
+    linked: HashMap<(u64, u64), TreeSize>, // Files with several links
+}
+
+impl TreeSizes {
+    /// Adds up the sizes of everything under a directory, including the
+    /// directory itself and hidden files, without following symlinks (like du)
+    pub fn size(&mut self, dir: &FileInfo) -> TreeSize {
+        let tree = self.tree(dir);
+        let mut total = tree.single;
+        for size in tree.linked.values() {
+            total.add(*size);
+        }
+        total
+    }
+
+    /// Walks a directory, or returns the tree remembered for it
+    fn tree(&mut self, dir: &FileInfo) -> Rc<Tree> {
+        let key = dir.metadata().map(|m| (m.dev(), m.ino()));
+        if let Some(tree) = key.and_then(|key| self.trees.get(&key)) {
+            return Rc::clone(tree);
+        }
+
+        let mut tree = Tree::default();
+        if let Some(metadata) = dir.metadata() {
+            tree.single.add(TreeSize::of(metadata));
+        }
+        match collect_entries(&dir.path, true, &Filter::default(), &dir.path) {
+            Ok(entries) => {
+                for entry in &entries {
+                    if entry.is_dir() {
+                        let subtree = self.tree(entry);
+                        tree.single.add(subtree.single);
+                        tree.linked.extend(&subtree.linked);
+                    } else if let Some(metadata) = entry.metadata() {
+                        if metadata.nlink() > 1 {
+                            let inode = (metadata.dev(), metadata.ino());
+                            tree.linked.insert(inode, TreeSize::of(metadata));
+                        } else {
+                            tree.single.add(TreeSize::of(metadata));
+                        }
+                    }
+                }
+            }
+            Err(e) => eprintln!("ls: {}: {e}", dir.path.display()),
+        }
+
+        let tree = Rc::new(tree);
+        if let Some(key) = key {
+            self.trees.insert(key, Rc::clone(&tree));
+        }
+        tree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_sizes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = |name| dir.path().join(name);
+        fs::create_dir_all(path("a/b")).unwrap();
+        fs::write(path("a/b/data"), vec![0u8; 1000]).unwrap();
+        fs::hard_link(path("a/b/data"), path("a/link")).unwrap();
+        fs::write(path("a/small"), "abc").unwrap();
+        let dir_size = |name| fs::metadata(path(name)).unwrap().len();
+
+        let mut sizes = TreeSizes::default();
+        let a = FileInfo::from_path(&path("a")).unwrap();
+        // The hard-linked file is counted once
+        let expected = dir_size("a") + dir_size("a/b") + 1000 + 3;
+        assert_eq!(sizes.size(&a).apparent, expected);
+
+        // Subtrees were remembered while walking their parent
+        assert_eq!(sizes.trees.len(), 2);
+        let b = FileInfo::from_path(&path("a/b")).unwrap();
+        assert_eq!(sizes.size(&b).apparent, dir_size("a/b") + 1000);
+        assert_eq!(sizes.trees.len(), 2);
+    }
+}
@@ -0,0 +1,315 @@
+//! Content duplicate finder for `--duplicates`.
+//!
+//! Files are first bucketed by size, so a file with a unique size is never
+//! read. Files that share a size are narrowed by an xxh3 hash of their first
+//! 4 KiB, and only those still matching are compared byte for byte, reading
+//! them all in step so each is read once. Hard links to one inode count as
+//! a single file, and empty files are ignored.
+
+use crate::directory::collect_tree;
+use crate::file_info::FileInfo;
+use crate::filter::Filter;
+use crate::summary::plural;
+use crate::utils::{format_size, BlockSize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use xxhash_rust::xxh3::{xxh3_64, Xxh3};
+
+/// Bytes read from the start of each candidate for the partial hash
+const PARTIAL_SIZE: u64 = 4096;
+
+/// Bytes read from each file per round of the byte comparison
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Files with identical content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateSet {
+    pub size: u64,           // Size of each file in bytes
+    pub hash: u64,           // xxh3 hash of the content
+    pub paths: Vec<PathBuf>, // One path per distinct inode, sorted
+}
+
+impl DuplicateSet {
+    /// Bytes freed by keeping a single copy
+    pub fn reclaimable(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+/// Duplicate sets found below one path
+#[derive(Debug, Default)]
+pub struct Duplicates {
+    pub path: PathBuf,           // Path that was searched
+    pub files: u64,              // Distinct non-empty regular files examined
+    pub sets: Vec<DuplicateSet>, // Biggest savings first
+}
+
+impl Duplicates {
+    /// Walks `path` and groups its regular files by content. Hidden entries
+    /// are skipped unless `show_all`, and entries failing `filter` are
+    /// ignored. Files that can't be read are reported and left out.
+    pub fn find(path: &Path, show_all: bool, filter: &Filter) -> io::Result<Self> {
+        let entries = if path.is_dir() {
+            collect_tree(path, show_all, filter)?
+        } else {
+            vec![FileInfo::from_path(path)?]
+        };
+
+        // Bucket by size, keeping one path per inode
+        let mut seen = HashSet::new();
+        let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+        for entry in entries {
+            let Some(metadata) = entry.metadata() else {
+                continue;
+            };
+            if !metadata.is_file()
+                || metadata.len() == 0
+                || !seen.insert((metadata.dev(), metadata.ino()))
+            {
+                continue;
+            }
+            by_size.entry(metadata.len()).or_default().push(entry.path);
+        }
+
+        let mut duplicates = Duplicates {
+            path: path.to_path_buf(),
+            files: seen.len() as u64,
+            sets: Vec::new(),
+        };
+        for (size, paths) in by_size {
+            if paths.len() < 2 {
+                continue;
+            }
+            for paths in group_by_hash(paths, PARTIAL_SIZE) {
+                for (hash, mut paths) in split_identical(paths) {
+                    paths.sort();
+                    duplicates.sets.push(DuplicateSet { size, hash, paths });
+                }
+            }
+        }
+        duplicates.sets.sort_by(|a, b| {
+            b.reclaimable()
+                .cmp(&a.reclaimable())
+                .then_with(|| a.paths.cmp(&b.paths))
+        });
+        Ok(duplicates)
+    }
+
+    /// Bytes freed by keeping one copy from every set
+    pub fn reclaimable(&self) -> u64 {
+        self.sets.iter().map(DuplicateSet::reclaimable).sum()
+    }
+
+    /// Renders the sets as text lines, with sizes in `unit`: a totals line,
+    /// then each set's size and savings followed by its indented paths
+    pub fn to_text(&self, unit: &BlockSize) -> Vec<String> {
+        let size = |bytes| format_size(bytes, unit).trim_start().to_string();
+        let copies = self.sets.iter().map(|set| set.paths.len() as u64 - 1).sum();
+        let mut lines = vec![format!(
+            "{} examined, {} in {}, {} reclaimable",
+            plural(self.files, "file", "files"),
+            plural(copies, "redundant copy", "redundant copies"),
+            plural(self.sets.len() as u64, "set", "sets"),
+            size(self.reclaimable())
+        )];
+        for set in &self.sets {
+            lines.push(format!(
+                "{} of {} ({} reclaimable):",
+                plural(set.paths.len() as u64, "file", "files"),
+                size(set.size),
+                size(set.reclaimable())
+            ));
+            for path in &set.paths {
+                lines.push(format!("  {}", path.display()));
+            }
+        }
+        lines
+    }
+
+    /// Renders the sets as a JSON object with sizes in bytes
+    pub fn to_json(&self) -> Value {
+        let sets: Vec<_> = self
+            .sets
+            .iter()
+            .map(|set| {
+                json!({
+                    "size": set.size,
+                    "hash": format!("{:016x}", set.hash),
+                    "reclaimable": set.reclaimable(),
+                    "paths": set.paths.iter().map(|p| p.to_string_lossy()).collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+
+        json!({
+            "path": self.path.to_string_lossy(),
+            "files": self.files,
+            "reclaimable": self.reclaimable(),
+            "sets": sets,
+        })
+    }
+}
+
+/// Splits files by the hash of their first `limit` bytes, keeping only
+/// hashes shared by two or more files
+fn group_by_hash(paths: Vec<PathBuf>, limit: u64) -> Vec<Vec<PathBuf>> {
+    let mut groups: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for path in paths {
+        match hash_start(&path, limit) {
+            Ok(hash) => groups.entry(hash).or_default().push(path),
+            Err(e) => eprintln!("ls: {}: {e}", path.display()),
+        }
+    }
+    groups
+        .into_values()
+        .filter(|paths| paths.len() > 1)
+        .collect()
+}
+
+/// Hashes the first `limit` bytes of a file with xxh3
+fn hash_start(path: &Path, limit: u64) -> io::Result<u64> {
+    let mut buffer = vec![0; limit as usize];
+    let n = read_full(&mut File::open(path)?, &mut buffer)?;
+    Ok(xxh3_64(&buffer[..n]))
+}
+
+/// A candidate file read in step with the others of its set
+struct Reader {
+    path: PathBuf,      // File being compared
+    file: Option<File>, // Opened on the first read and closed at the end of the file
+}
+
+impl Reader {
+    /// Reads the next chunk of the file into `buffer`, returning its length.
+    /// A chunk shorter than `buffer` is the last one.
+    fn next_chunk(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => self.file.insert(File::open(&self.path)?),
+        };
+        let n = read_full(file, buffer)?;
+        if n < buffer.len() {
+            self.file = None;
+        }
+        Ok(n)
+    }
+}
+
+/// Splits files of one size into sets whose content is identical, keeping
+/// only sets of two or more, each with the xxh3 hash of its content. Every
+/// round reads the next chunk of each file still in a set and splits the
+/// set by what was read, so each file is read once.
+fn split_identical(paths: Vec<PathBuf>) -> Vec<(u64, Vec<PathBuf>)> {
+    let readers: Vec<Reader> = paths
+        .into_iter()
+        .map(|path| Reader { path, file: None })
+        .collect();
+    let mut pending = vec![(Xxh3::new(), readers)];
+    let mut sets = Vec::new();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    while let Some((hasher, readers)) = pending.pop() {
+        // Files of this set by the chunk they read
+        let mut splits: Vec<(Vec<u8>, Vec<Reader>)> = Vec::new();
+        for mut reader in readers {
+            let chunk = match reader.next_chunk(&mut buffer) {
+                Ok(n) => &buffer[..n],
+                Err(e) => {
+                    eprintln!("ls: {}: {e}", reader.path.display());
+                    continue;
+                }
+            };
+            match splits.iter_mut().find(|(bytes, _)| bytes == chunk) {
+                Some((_, same)) => same.push(reader),
+                None => splits.push((chunk.to_vec(), vec![reader])),
+            }
+        }
+
+        for (chunk, readers) in splits {
+            if readers.len() < 2 {
+                continue;
+            }
+            let mut hasher = hasher.clone();
+            hasher.update(&chunk);
+            if chunk.len() < CHUNK_SIZE {
+                let paths = readers.into_iter().map(|reader| reader.path).collect();
+                sets.push((hasher.digest(), paths));
+            } else {
+                pending.push((hasher, readers));
+            }
+        }
+    }
+    sets
+}
+
+/// Reads until `buffer` is full or the file ends, returning the bytes read
+fn read_full(file: &mut File, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match file.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_find_duplicates() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = |name| dir.path().join(name);
+        // Same size and first 4 KiB, differing only at the end
+        let mut long = vec![b'x'; 10_000];
+        fs::write(path("long1"), &long).unwrap();
+        fs::write(path("long2"), &long).unwrap();
+        long[9_999] = b'y';
+        fs::write(path("long3"), &long).unwrap();
+        fs::write(path("small1"), "hello").unwrap();
+        fs::write(path("small2"), "hello").unwrap();
+        fs::hard_link(path("small1"), path("small3")).unwrap();
+        fs::write(path("other"), "world").unwrap();
+        fs::write(path("empty1"), "").unwrap();
+        fs::write(path("empty2"), "").unwrap();
+
+        let duplicates = Duplicates::find(dir.path(), false, &Filter::default()).unwrap();
+        let sets: Vec<(u64, usize)> = duplicates
+            .sets
+            .iter()
+            .map(|set| (set.size, set.paths.len()))
+            .collect();
+        assert_eq!(sets, [(10_000, 2), (5, 2)]);
+        assert_eq!(duplicates.sets[0].paths, [path("long1"), path("long2")]);
+        assert_eq!(duplicates.files, 6);
+        assert_eq!(duplicates.reclaimable(), 10_005);
+    }
+
+    #[test]
+    fn test_split_identical() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = |name| dir.path().join(name);
+        // As if the partial hashes had collided: "b" differs only in its
+        // second chunk, and "a" files end exactly on a chunk boundary
+        let mut content = vec![b'x'; 2 * CHUNK_SIZE];
+        fs::write(path("a1"), &content).unwrap();
+        fs::write(path("a2"), &content).unwrap();
+        fs::write(path("a3"), &content).unwrap();
+        content[CHUNK_SIZE + 1] = b'y';
+        fs::write(path("b"), &content).unwrap();
+
+        let sets = split_identical(vec![path("a1"), path("b"), path("a2"), path("a3")]);
+        assert_eq!(sets.len(), 1);
+        let (hash, paths) = &sets[0];
+        assert_eq!(*paths, [path("a1"), path("a2"), path("a3")]);
+        assert_eq!(*hash, xxh3_64(&vec![b'x'; 2 * CHUNK_SIZE]));
+    }
+}
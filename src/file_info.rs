@@ -2,18 +2,40 @@
 //!
 //! This module provides the FileInfo struct which encapsulates file metadata
 //! and provides convenient accessor methods for file properties.
+//!
+//! Entries read from a directory start out with only their name and type
+//! (from the directory's d_type). The full metadata is fetched with lstat
+//! the first time something needs it, so a plain name listing never stats.
 
-use std::fs::{self, DirEntry, Metadata};
-use std::os::unix::fs::MetadataExt;
+use crate::acl;
+use crate::attrs;
+use crate::caps::{self, Capabilities};
+use crate::directory::TreeSize;
+use crate::statx::{self, Statx};
+use crate::utils::TimeField;
+use crate::xattr;
+use std::cell::OnceCell;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, DirEntry, FileType, Metadata};
+use std::io;
+use std::os::unix::fs::{DirEntryExt, MetadataExt, PermissionsExt};
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
 /// Represents information about a single file or directory.
 /// Stores the path, file name, and metadata for efficient access.
 pub struct FileInfo {
-    pub path: PathBuf,      // Full path to the file
-    pub file_name: String,  // File name (extracted from path for efficiency)
-    pub metadata: Metadata, // File system metadata
+    pub path: PathBuf,                        // Full path to the file
+    pub file_name: String,                    // File name (extracted from path for efficiency)
+    pub raw_name: OsString,                   // File name exactly as stored, for quoting on display
+    file_type: FileType,                      // File type, known without a stat
+    ino: u64,                                 // Inode number, known without a stat
+    metadata: OnceCell<io::Result<Metadata>>, // File system metadata, read on first use
+    xattrs: OnceCell<Vec<OsString>>,          // Extended attribute names, read on first use
+    context: OnceCell<Option<String>>,        // SELinux security context, read on first use
+    statx: OnceCell<Option<Statx>>,           // Birth time, mount id, flags, read on first use
+    tree_size: OnceCell<TreeSize>,            // Directory tree size (--total-size), once computed
+    link_group: OnceCell<usize>,              // Hard link group number in the listing (--links)
 }
 
 impl FileInfo {
@@ -21,13 +43,23 @@ impl FileInfo {
     /// Note: Takes DirEntry by value because its methods consume self
     #[allow(clippy::needless_pass_by_value)]
     pub fn from_entry(entry: DirEntry) -> std::io::Result<Self> {
-        let metadata = entry.metadata()?;
+        // Uses d_type, only falling back to lstat on file systems without it
+        let file_type = entry.file_type()?;
         let path = entry.path();
-        let file_name = entry.file_name().to_string_lossy().to_string();
+        let raw_name = entry.file_name();
+        let file_name = raw_name.to_string_lossy().to_string();
         Ok(FileInfo {
             path,
             file_name,
-            metadata,
+            raw_name,
+            file_type,
+            ino: entry.ino(),
+            metadata: OnceCell::new(),
+            xattrs: OnceCell::new(),
+            context: OnceCell::new(),
+            statx: OnceCell::new(),
+            tree_size: OnceCell::new(),
+            link_group: OnceCell::new(),
         })
     }
 
@@ -35,33 +67,107 @@ impl FileInfo {
     pub fn from_path(path: &Path) -> std::io::Result<Self> {
         // Use symlink_metadata to detect symlinks properly
         let metadata = fs::symlink_metadata(path)?;
-        let file_name = path
+        let raw_name = path
             .file_name()
-            .unwrap_or_else(|| std::ffi::OsStr::new("."))
-            .to_string_lossy()
-            .to_string();
+            .unwrap_or_else(|| OsStr::new("."))
+            .to_os_string();
+        let file_name = raw_name.to_string_lossy().to_string();
         Ok(FileInfo {
             path: path.to_path_buf(),
             file_name,
-            metadata,
+            raw_name,
+            file_type: metadata.file_type(),
+            ino: metadata.ino(),
+            metadata: OnceCell::from(Ok(metadata)),
+            xattrs: OnceCell::new(),
+            context: OnceCell::new(),
+            statx: OnceCell::new(),
+            tree_size: OnceCell::new(),
+            link_group: OnceCell::new(),
         })
     }
 
-    /// Returns modification time for sorting
-    pub fn modified_time(&self) -> SystemTime {
-        self.metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)
+    /// Renames the entry to its path below `root` (e.g. "src/main.rs"),
+    /// for flat listings
+    pub fn relative_to(&mut self, root: &Path) {
+        if let Ok(relative) = self.path.strip_prefix(root) {
+            self.raw_name = relative.as_os_str().to_os_string();
+            self.file_name = self.raw_name.to_string_lossy().to_string();
+        }
+    }
+
+    /// Returns the file metadata, reading it on first use.
+    /// Returns None if the file can no longer be read, e.g., because it was
+    /// deleted after the directory was listed; see `metadata_error`.
+    pub fn metadata(&self) -> Option<&Metadata> {
+        self.metadata
+            .get_or_init(|| fs::symlink_metadata(&self.path))
+            .as_ref()
+            .ok()
+    }
+
+    /// Returns the error from reading the metadata, if it was read and failed.
+    /// Never reads the metadata itself, so untouched entries report nothing.
+    pub fn metadata_error(&self) -> Option<&io::Error> {
+        self.metadata.get().and_then(|result| result.as_ref().err())
+    }
+
+    /// Returns the file type without reading the full metadata
+    pub fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    /// Returns the permission bits and file type bits (st_mode), or 0 if
+    /// the metadata can't be read
+    pub fn mode(&self) -> u32 {
+        self.metadata().map_or(0, |m| m.permissions().mode())
+    }
+
+    /// Returns the selected timestamp, or None if it is unavailable
+    /// (e.g., birth time on a file system that doesn't record it)
+    pub fn time(&self, field: TimeField) -> Option<SystemTime> {
+        let metadata = self.metadata()?;
+        match field {
+            TimeField::Mtime => metadata.modified().ok(),
+            TimeField::Atime => metadata.accessed().ok(),
+            TimeField::Ctime => {
+                statx::timestamp(metadata.ctime(), u32::try_from(metadata.ctime_nsec()).ok()?)
+            }
+            TimeField::Birth => self.birth_time(),
+        }
+    }
+
+    /// Records the hard link group this entry belongs to (--links)
+    pub fn set_link_group(&self, group: usize) {
+        // Assigned once per listing; a second call would carry the same value
+        let _ = self.link_group.set(group);
+    }
+
+    /// Returns the entry's hard link group, if other listed entries share its inode
+    pub fn link_group(&self) -> Option<usize> {
+        self.link_group.get().copied()
+    }
+
+    /// Records the size of a directory's whole tree, which then replaces
+    /// the directory's own size in `size()` and `blocks()`
+    pub fn set_tree_size(&self, size: TreeSize) {
+        // Computed once per entry; a second call would carry the same value
+        let _ = self.tree_size.set(size);
     }
 
     /// Returns the block count for the file
     pub fn blocks(&self) -> u64 {
+        if let Some(tree) = self.tree_size.get() {
+            return tree.allocated / 512;
+        }
         #[cfg(unix)]
         {
-            self.metadata.blocks()
+            self.metadata().map_or(0, MetadataExt::blocks)
         }
         #[cfg(not(unix))]
         {
             // Fallback for non-Unix: estimate blocks from size
-            self.metadata.len().div_ceil(512)
+            self.size().div_ceil(512)
         }
     }
 
@@ -72,42 +178,136 @@ impl FileInfo {
 
     /// Check if this is a directory
     pub fn is_dir(&self) -> bool {
-        self.metadata.is_dir()
+        self.file_type.is_dir()
     }
 
     /// Get symlink target if this is a symlink
     pub fn symlink_target(&self) -> Option<PathBuf> {
-        if self.metadata.is_symlink() {
+        if self.file_type.is_symlink() {
             fs::read_link(&self.path).ok()
         } else {
             None
         }
     }
 
+    /// Get metadata of the file a symlink points to, following all links
+    pub fn target_metadata(&self) -> Option<Metadata> {
+        fs::metadata(&self.path).ok()
+    }
+
+    /// Get extended attribute names (empty if unsupported or unreadable)
+    pub fn xattr_names(&self) -> &[OsString] {
+        self.xattrs
+            .get_or_init(|| xattr::list(&self.path).unwrap_or_default())
+    }
+
+    /// Get the value of an extended attribute, if present and readable
+    pub fn xattr_value(&self, name: &OsStr) -> Option<Vec<u8>> {
+        xattr::get(&self.path, name).ok().flatten()
+    }
+
+    /// Check whether the file has a POSIX access or default ACL
+    pub fn has_acl(&self) -> bool {
+        self.xattr_names()
+            .iter()
+            .any(|name| acl::is_acl_attribute(name))
+    }
+
+    /// Get the file's capability sets, if it has any
+    pub fn capabilities(&self) -> Option<Capabilities> {
+        // Check the cached name list first to avoid a lookup per file
+        if !self
+            .xattr_names()
+            .iter()
+            .any(|name| name == caps::ATTRIBUTE)
+        {
+            return None;
+        }
+        caps::parse(&self.xattr_value(OsStr::new(caps::ATTRIBUTE))?)
+    }
+
+    /// Get the statx fields, or None if statx is unavailable on this
+    /// kernel or file system
+    pub fn statx(&self) -> Option<&Statx> {
+        self.statx
+            .get_or_init(|| statx::statx(&self.path).ok())
+            .as_ref()
+    }
+
+    /// Get the creation time, if the file system records it
+    pub fn birth_time(&self) -> Option<SystemTime> {
+        self.statx()?.birth_time
+    }
+
+    /// Get the id of the mount containing the file
+    pub fn mount_id(&self) -> Option<u64> {
+        self.statx()?.mount_id
+    }
+
+    /// Get the STATX_ATTR_* flags (immutable, append, encrypted, verity,
+    /// dax, ...) that are set, or 0 if statx is unavailable
+    pub fn statx_attributes(&self) -> u64 {
+        self.statx().map_or(0, |statx| statx.attributes)
+    }
+
+    /// Get the inode flags (immutable, append-only, ...), if the file
+    /// system supports them. Only regular files and directories are
+    /// queried, since opening a device or FIFO can have side effects.
+    /// Flags that statx also reports are merged in, so they show up even
+    /// where the ioctl is unsupported.
+    pub fn inode_flags(&self) -> Option<u32> {
+        let ioctl = if self.file_type.is_file() || self.file_type.is_dir() {
+            attrs::read(&self.path).ok()
+        } else {
+            None
+        };
+        let statx = self
+            .statx()
+            .map(|_| attrs::from_statx(self.statx_attributes()));
+        match (ioctl, statx) {
+            (None, None) => None,
+            (ioctl, statx) => Some(ioctl.unwrap_or(0) | statx.unwrap_or(0)),
+        }
+    }
+
+    /// Get the SELinux security context (e.g., "system_u:object_r:bin_t:s0"),
+    /// if the file has one
+    pub fn security_context(&self) -> Option<&str> {
+        self.context
+            .get_or_init(|| {
+                let value = self.xattr_value(OsStr::new("security.selinux"))?;
+                // The kernel stores the context with a trailing NUL
+                let value = value.strip_suffix(&[0]).unwrap_or(&value);
+                Some(String::from_utf8_lossy(value).into_owned())
+            })
+            .as_deref()
+    }
+
     /// Get file size
     pub fn size(&self) -> u64 {
-        self.metadata.len()
+        if let Some(tree) = self.tree_size.get() {
+            return tree.apparent;
+        }
+        self.metadata().map_or(0, Metadata::len)
     }
 
     /// Get user ID
     pub fn uid(&self) -> u32 {
-        self.metadata.uid()
+        self.metadata().map_or(0, MetadataExt::uid)
     }
 
     /// Get group ID
     pub fn gid(&self) -> u32 {
-        self.metadata.gid()
+        self.metadata().map_or(0, MetadataExt::gid)
     }
 
-    /// Get number of hard links
-    pub fn nlink(&self) -> u64 {
-        self.metadata.nlink()
+    /// Get inode number
+    pub fn inode(&self) -> u64 {
+        self.ino
     }
 
-    /// Get modified time
-    pub fn modified(&self) -> io::Result<SystemTime> {
-        self.metadata.modified()
+    /// Get number of hard links
+    pub fn nlink(&self) -> u64 {
+        self.metadata().map_or(0, MetadataExt::nlink)
     }
 }
-
-use std::io;
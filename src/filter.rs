@@ -0,0 +1,523 @@
+//! Entry filters: `--type`, `--size`, `--newer`, `--older`, `--mtime`,
+//! `--owner`, `--group`, `--perm`, `--where`, and the name patterns
+//! `--match`, `--regex` and `--exclude`.
+//!
+//! Each option parses into one Test, and an entry is listed only if it
+//! passes every test. The syntax follows `find`: `+N` means more than N,
+//! `-N` less than N, and a bare N exactly N.
+
+use crate::file_info::FileInfo;
+use crate::ids::{IdResolver, IdSource};
+use crate::query::{Context, Query};
+use crate::utils::TimeField;
+use glob::{MatchOptions, Pattern};
+use regex::{Regex, RegexBuilder};
+use std::borrow::Cow;
+use std::fs::{FileType, Metadata};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::Path;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+use users::{get_group_by_name, get_user_by_name};
+
+/// How a number given as `+N`, `-N` or `N` is compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Greater, // +N
+    Less,    // -N
+    Equal,   // N
+}
+
+impl Comparison {
+    /// Splits the sign off a `find`-style number
+    fn split(s: &str) -> (Comparison, &str) {
+        if let Some(rest) = s.strip_prefix('+') {
+            (Comparison::Greater, rest)
+        } else if let Some(rest) = s.strip_prefix('-') {
+            (Comparison::Less, rest)
+        } else {
+            (Comparison::Equal, s)
+        }
+    }
+
+    /// Compares a value against the limit
+    fn test(self, value: u64, limit: u64) -> bool {
+        match self {
+            Comparison::Greater => value > limit,
+            Comparison::Less => value < limit,
+            Comparison::Equal => value == limit,
+        }
+    }
+}
+
+/// File types accepted by --type, e.g. "f,d"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeSet(Vec<char>);
+
+impl TypeSet {
+    /// Whether a file type is in the set
+    fn contains(&self, file_type: FileType) -> bool {
+        self.0.contains(&type_letter(file_type))
+    }
+}
+
+/// Returns the `find` letter for a file type: f, d, l, p, s, b or c
+pub fn type_letter(file_type: FileType) -> char {
+    if file_type.is_dir() {
+        'd'
+    } else if file_type.is_symlink() {
+        'l'
+    } else if file_type.is_fifo() {
+        'p'
+    } else if file_type.is_socket() {
+        's'
+    } else if file_type.is_block_device() {
+        'b'
+    } else if file_type.is_char_device() {
+        'c'
+    } else {
+        'f'
+    }
+}
+
+impl FromStr for TypeSet {
+    type Err = String;
+
+    /// Parses a comma-separated list of `find` type letters (f, d, l, p, s, b, c)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(|letter| match letter {
+                "f" | "d" | "l" | "p" | "s" | "b" | "c" => Ok(letter.chars().next().unwrap_or('f')),
+                _ => Err(format!(
+                    "invalid file type: '{letter}' (expected f, d, l, p, s, b or c)"
+                )),
+            })
+            .collect::<Result<_, _>>()
+            .map(TypeSet)
+    }
+}
+
+/// A size bound for --size, e.g. "+10M" or "-1k"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeTest {
+    comparison: Comparison, // Larger, smaller or exactly
+    bytes: u64,             // The size in bytes
+}
+
+impl FromStr for SizeTest {
+    type Err = String;
+
+    /// Parses an optionally signed size with an optional unit: `c` for
+    /// bytes, or `k`, `M`, `G`, `T` for powers of 1024 (case-insensitive)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid size: '{s}'");
+        let (comparison, rest) = Comparison::split(s);
+        let digits_end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (count, unit) = rest.split_at(digits_end);
+        let count: u64 = count.parse().map_err(|_| invalid())?;
+        let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+            "" | "c" => 1,
+            "k" => 1 << 10,
+            "m" => 1 << 20,
+            "g" => 1 << 30,
+            "t" => 1 << 40,
+            _ => return Err(invalid()),
+        };
+        Ok(SizeTest {
+            comparison,
+            bytes: count.checked_mul(multiplier).ok_or_else(invalid)?,
+        })
+    }
+}
+
+/// A `--size` argument: bare, it is the long form of `-s`; with a value,
+/// it is a size test (`--size=+10M`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeOption {
+    Blocks,         // --size: print allocated sizes
+    Test(SizeTest), // --size=N: filter by apparent size
+}
+
+impl FromStr for SizeOption {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            Ok(SizeOption::Blocks)
+        } else {
+            s.parse().map(SizeOption::Test)
+        }
+    }
+}
+
+/// An age bound for --mtime, e.g. "-7d" (changed in the last week)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AgeTest {
+    comparison: Comparison, // Older, newer or exactly
+    count: u64,             // Number of units
+    unit: u64,              // Unit length in seconds
+}
+
+impl FromStr for AgeTest {
+    type Err = String;
+
+    /// Parses an optionally signed age with an optional unit: `s`, `m`,
+    /// `h`, `d` (the default) or `w`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid age: '{s}'");
+        let (comparison, rest) = Comparison::split(s);
+        let digits_end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (count, unit) = rest.split_at(digits_end);
+        let unit = match unit {
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "" | "d" => 24 * 60 * 60,
+            "w" => 7 * 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        Ok(AgeTest {
+            comparison,
+            count: count.parse().map_err(|_| invalid())?,
+            unit,
+        })
+    }
+}
+
+impl AgeTest {
+    /// Tests an age; like `find -mtime`, a bare N matches ages that round
+    /// down to N units
+    fn test(self, age: Duration) -> bool {
+        let seconds = age.as_secs();
+        match self.comparison {
+            Comparison::Equal => seconds / self.unit == self.count,
+            comparison => comparison.test(seconds, self.count.saturating_mul(self.unit)),
+        }
+    }
+}
+
+/// A permission test for --perm, e.g. "0644", "-u+x" or "/o+w"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PermTest {
+    comparison: Comparison, // Equal: exact mode, Less ('-'): all bits set, Greater ('/'): any bit set
+    mode: u32,              // Permission bits
+}
+
+impl FromStr for PermTest {
+    type Err = String;
+
+    /// Parses an octal mode or symbolic clauses such as "u+x,g=rw",
+    /// optionally prefixed with `-` (all bits set) or `/` (any bit set)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid mode: '{s}'");
+        let (comparison, rest) = if let Some(rest) = s.strip_prefix('-') {
+            (Comparison::Less, rest)
+        } else if let Some(rest) = s.strip_prefix('/') {
+            (Comparison::Greater, rest)
+        } else {
+            (Comparison::Equal, s)
+        };
+
+        let mode = if !rest.is_empty() && rest.chars().all(|c| c.is_digit(8)) {
+            u32::from_str_radix(rest, 8).map_err(|_| invalid())?
+        } else {
+            parse_symbolic(rest).ok_or_else(invalid)?
+        };
+        if mode > 0o7777 {
+            return Err(invalid());
+        }
+        Ok(PermTest { comparison, mode })
+    }
+}
+
+impl PermTest {
+    /// Tests the permission bits of a mode
+    fn test(self, mode: u32) -> bool {
+        let mode = mode & 0o7777;
+        match self.comparison {
+            Comparison::Equal => mode == self.mode,
+            Comparison::Less => mode & self.mode == self.mode,
+            Comparison::Greater => self.mode == 0 || mode & self.mode != 0,
+        }
+    }
+}
+
+/// Converts symbolic clauses ("u+x,go=r") to permission bits. Each clause
+/// is who letters (`ugoa`, default `a`), `+` or `=`, and `rwxst` letters.
+fn parse_symbolic(s: &str) -> Option<u32> {
+    let mut mode = 0;
+    for clause in s.split(',') {
+        let op = clause.find(['+', '='])?;
+        let (who, perms) = (&clause[..op], &clause[op + 1..]);
+        let who = if who.is_empty() { "a" } else { who };
+
+        let mut classes = 0;
+        for c in who.chars() {
+            classes |= match c {
+                'u' => 0o4700,
+                'g' => 0o2070,
+                'o' => 0o1007,
+                'a' => 0o7777,
+                _ => return None,
+            };
+        }
+        let mut bits = 0;
+        for c in perms.chars() {
+            bits |= match c {
+                'r' => 0o0444,
+                'w' => 0o0222,
+                'x' => 0o0111,
+                's' => 0o6000,
+                't' => 0o1000,
+                _ => return None,
+            };
+        }
+        mode |= classes & bits;
+    }
+    Some(mode)
+}
+
+/// A name pattern for --match, --regex, --exclude and their variants
+#[derive(Debug, Clone)]
+pub enum NamePattern {
+    Glob(Pattern, MatchOptions), // Shell pattern matching the whole name
+    Regex(Regex),                // Regular expression matching anywhere in the name
+}
+
+impl NamePattern {
+    /// Whether a name matches the pattern
+    fn is_match(&self, name: &str) -> bool {
+        match self {
+            NamePattern::Glob(pattern, options) => pattern.matches_with(name, *options),
+            NamePattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+/// Parses a shell pattern for --match and --exclude
+pub fn glob_pattern(s: &str) -> Result<NamePattern, String> {
+    parse_glob(s, true)
+}
+
+/// Parses a case-insensitive shell pattern for --imatch and --iexclude
+pub fn iglob_pattern(s: &str) -> Result<NamePattern, String> {
+    parse_glob(s, false)
+}
+
+/// Parses a regular expression for --regex and --exclude-regex
+pub fn regex_pattern(s: &str) -> Result<NamePattern, String> {
+    parse_regex(s, false)
+}
+
+/// Parses a case-insensitive regular expression for --iregex and --iexclude-regex
+pub fn iregex_pattern(s: &str) -> Result<NamePattern, String> {
+    parse_regex(s, true)
+}
+
+fn parse_glob(s: &str, case_sensitive: bool) -> Result<NamePattern, String> {
+    let options = MatchOptions {
+        case_sensitive,
+        ..MatchOptions::new()
+    };
+    Pattern::new(s)
+        .map(|pattern| NamePattern::Glob(pattern, options))
+        .map_err(|e| format!("invalid pattern '{s}': {}", e.msg))
+}
+
+fn parse_regex(s: &str, case_insensitive: bool) -> Result<NamePattern, String> {
+    RegexBuilder::new(s)
+        .case_insensitive(case_insensitive)
+        .build()
+        .map(NamePattern::Regex)
+        .map_err(|e| format!("invalid regular expression '{s}': {e}"))
+}
+
+/// Reads the modification time of a reference file for --newer and --older
+pub fn reference_time(path: &str) -> Result<SystemTime, String> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| format!("cannot stat '{path}': {e}"))
+}
+
+/// Resolves a user name or numeric uid for --owner
+pub fn parse_user(name: &str) -> Result<u32, String> {
+    name.parse()
+        .ok()
+        .or_else(|| get_user_by_name(name).map(|user| user.uid()))
+        .ok_or_else(|| format!("invalid user: '{name}'"))
+}
+
+/// Resolves a group name or numeric gid for --group
+pub fn parse_group(name: &str) -> Result<u32, String> {
+    name.parse()
+        .ok()
+        .or_else(|| get_group_by_name(name).map(|group| group.gid()))
+        .ok_or_else(|| format!("invalid group: '{name}'"))
+}
+
+/// One condition an entry must meet to be listed
+#[derive(Debug, Clone)]
+pub enum Test {
+    Type(TypeSet),             // File type is one of these (--type)
+    Size(SizeTest),            // Apparent size (--size)
+    Newer(SystemTime),         // Modified after this time (--newer)
+    Older(SystemTime),         // Modified before this time (--older)
+    Age(AgeTest),              // Time since modification (--mtime)
+    Owner(u32),                // Owned by this uid (--owner)
+    Group(u32),                // Owned by this gid (--group)
+    Perm(PermTest),            // Permission bits (--perm)
+    Where(Query),              // Query expression (--where)
+    Include(Vec<NamePattern>), // Name matches one of these (--match, --regex)
+    Exclude(Vec<NamePattern>), // Name matches none of these (--exclude, --exclude-regex)
+}
+
+/// The tests every listed entry must pass
+pub struct Filter {
+    tests: Vec<Test>,    // All must pass (AND)
+    now: SystemTime,     // Reference time for --mtime and `now` in --where
+    ids: Rc<IdResolver>, // Name lookups for `user` and `group` in --where, shared with the listing
+    full_path: bool,     // Match name patterns against the path below the root (--full-path)
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::new(
+            Vec::new(),
+            false,
+            Rc::new(IdResolver::new(IdSource::default())),
+        )
+    }
+}
+
+impl Filter {
+    /// Creates a filter from a list of tests. With `full_path`, name
+    /// patterns see the path relative to the listed root instead of the name.
+    /// `ids` resolves the `user` and `group` fields of --where expressions.
+    pub fn new(tests: Vec<Test>, full_path: bool, ids: Rc<IdResolver>) -> Self {
+        Filter {
+            tests,
+            now: SystemTime::now(),
+            ids,
+            full_path,
+        }
+    }
+
+    /// Whether no tests are set, so every entry passes
+    pub fn is_empty(&self) -> bool {
+        self.tests.is_empty()
+    }
+
+    /// Whether an entry passes every test. --type and the name patterns
+    /// are answered without reading the entry's metadata, and --where reads
+    /// only what its fields need; entries whose metadata can't be read fail
+    /// the other tests. `root` is the path being listed, which --full-path
+    /// patterns are relative to.
+    pub fn matches(&self, file_info: &FileInfo, root: &Path) -> bool {
+        self.tests
+            .iter()
+            .all(|test| self.passes(test, file_info, root))
+    }
+
+    /// Runs one test
+    fn passes(&self, test: &Test, file_info: &FileInfo, root: &Path) -> bool {
+        let metadata = |check: &dyn Fn(&Metadata) -> bool| file_info.metadata().is_some_and(check);
+        let modified = |check: &dyn Fn(SystemTime) -> bool| {
+            file_info.time(TimeField::Mtime).is_some_and(check)
+        };
+        match test {
+            Test::Type(types) => types.contains(file_info.file_type()),
+            Test::Size(size) => metadata(&|m| size.comparison.test(m.len(), size.bytes)),
+            Test::Newer(time) => modified(&|modified| modified > *time),
+            Test::Older(time) => modified(&|modified| modified < *time),
+            Test::Age(age) => modified(&|modified| {
+                age.test(self.now.duration_since(modified).unwrap_or_default())
+            }),
+            Test::Owner(uid) => metadata(&|m| m.uid() == *uid),
+            Test::Group(gid) => metadata(&|m| m.gid() == *gid),
+            Test::Perm(perm) => metadata(&|m| perm.test(m.mode())),
+            Test::Where(query) => query.matches(file_info, &self.context()),
+            Test::Include(patterns) => {
+                let name = self.match_name(file_info, root);
+                patterns.iter().any(|pattern| pattern.is_match(&name))
+            }
+            Test::Exclude(patterns) => {
+                let name = self.match_name(file_info, root);
+                !patterns.iter().any(|pattern| pattern.is_match(&name))
+            }
+        }
+    }
+
+    /// The string name patterns are matched against: the file name, or
+    /// with --full-path the path below the listed root (e.g. "src/main.rs")
+    fn match_name<'a>(&self, file_info: &'a FileInfo, root: &Path) -> Cow<'a, str> {
+        if !self.full_path {
+            return Cow::Borrowed(&file_info.file_name);
+        }
+        let path = file_info.path.strip_prefix(root).unwrap_or(&file_info.path);
+        Cow::Owned(path.to_string_lossy().into_owned())
+    }
+
+    /// What --where expressions are evaluated with
+    fn context(&self) -> Context<'_> {
+        Context {
+            now: self.now,
+            ids: &self.ids,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size_and_age() {
+        let size: SizeTest = "+10M".parse().unwrap();
+        assert!(size.comparison.test(10 * 1024 * 1024 + 1, size.bytes));
+        assert!(!size.comparison.test(10 * 1024 * 1024, size.bytes));
+        assert_eq!("-1k".parse::<SizeTest>().unwrap().bytes, 1024);
+        assert_eq!(
+            "12c".parse::<SizeTest>().unwrap().comparison,
+            Comparison::Equal
+        );
+        assert!("10X".parse::<SizeTest>().is_err());
+
+        let week: AgeTest = "-7d".parse().unwrap();
+        assert!(week.test(Duration::from_secs(6 * 86400)));
+        assert!(!week.test(Duration::from_secs(8 * 86400)));
+        assert!("2"
+            .parse::<AgeTest>()
+            .unwrap()
+            .test(Duration::from_secs(2 * 86400 + 5)));
+        assert!("+1h"
+            .parse::<AgeTest>()
+            .unwrap()
+            .test(Duration::from_secs(3601)));
+    }
+
+    #[test]
+    fn test_parse_perm_and_types() {
+        let exact: PermTest = "0644".parse().unwrap();
+        assert!(exact.test(0o100_644));
+        assert!(!exact.test(0o100_755));
+
+        let user_exec: PermTest = "-u+x".parse().unwrap();
+        assert_eq!(user_exec.mode, 0o100);
+        assert!(user_exec.test(0o755));
+        assert!(!user_exec.test(0o644));
+
+        let any_write: PermTest = "/go+w".parse().unwrap();
+        assert!(any_write.test(0o620));
+        assert!(!any_write.test(0o600));
+        assert_eq!("u=rw,g=r,o=r".parse::<PermTest>().unwrap(), exact);
+        assert!("u+q".parse::<PermTest>().is_err());
+
+        assert_eq!("f,d".parse::<TypeSet>().unwrap(), TypeSet(vec!['f', 'd']));
+        assert!("f,x".parse::<TypeSet>().is_err());
+    }
+}
@@ -3,53 +3,233 @@
 //! This module provides a flexible formatter that can display files in different formats
 //! using the Display trait, making it easy to test and extend.
 
+use crate::acl::{self, AclEntry, Tag};
+use crate::attrs;
 use crate::file_info::FileInfo;
+use crate::ids::{IdResolver, IdSource};
+use crate::quoting::{quote_name, quote_name_within, QuotingStyle};
 use crate::utils::{
-    colorize_name, format_block_size, format_permissions, format_size_human, format_time,
+    age_bucket, colorize_capability, colorize_name, colorize_time, display_width, file_type_char,
+    format_block_size, format_permissions, format_size, format_time, indicator, pad_left,
+    pad_right, BlockSize, IndicatorStyle, TimeField, TimeStyle,
 };
+use crate::xattr;
+use colored::control::SHOULD_COLORIZE;
+use colored::{ColoredString, Colorize};
+use std::ffi::OsStr;
 use std::fmt;
+use std::os::unix::fs::PermissionsExt;
+use std::rc::Rc;
 use std::time::SystemTime;
-use users::{get_group_by_gid, get_user_by_uid};
 
-/// Display format for file entries
-#[derive(Debug, PartialEq, Clone)]
+/// Display format for a listing, as selected by `--format` or its short flags
+#[derive(Debug, PartialEq, Clone, Copy, clap::ValueEnum)]
 pub enum Format {
-    Name,     // Display only the file name (colored)
-    WithSize, // Display size followed by file name
-    Long,     // Display full details (permissions, owner, size, date, name)
+    #[value(alias = "horizontal")]
+    Across, // Names in columns, filled row by row (-x)
+    Commas, // Names separated by commas, wrapped at the line width (-m)
+    #[value(alias = "verbose")]
+    Long, // Display full details (permissions, owner, size, date, name) (-l)
+    SingleColumn, // One name per line (-1)
+    Vertical, // Names in columns, filled column by column (-C)
+}
+
+/// Display settings shared by every entry rendered during a run
+#[allow(clippy::struct_excessive_bools)]
+pub struct FormatConfig {
+    pub format: Format,                  // The format to use for display
+    pub size_unit: BlockSize,            // Unit for the long format size column
+    pub block_size: BlockSize,           // Unit for -s sizes and the long format total
+    pub show_inode: bool,                // Prefix each entry with its inode number
+    pub show_blocks: bool,               // Prefix each entry with its allocated size (-s)
+    pub numeric_ids: bool,               // Show numeric uid/gid instead of names
+    pub show_owner: bool,                // Include the owner column in long format
+    pub show_group: bool,                // Include the group column in long format
+    pub indicator_style: IndicatorStyle, // Type indicators appended to names
+    pub quoting_style: QuotingStyle,     // How names are quoted and escaped
+    pub hide_control: bool,              // Replace control characters with '?'
+    pub full_paths: bool,                // Show each entry's full path instead of its name
+    pub time_style: TimeStyle,           // How timestamps are rendered in long format
+    pub time_field: TimeField,           // Which timestamp the date column shows (--time-field)
+    pub color_age: bool,                 // Shade the date column by file age
+    pub now: SystemTime,                 // Reference time for relative dates and age shading
+    pub line_width: usize,               // Terminal width for grid and comma layouts
+    pub max_name_width: Option<usize>,   // Truncate longer names with an ellipsis in grids
+    pub show_xattrs: bool,               // List extended attributes under each entry (-@)
+    pub xattr_preview: bool,             // Include a preview of each attribute value
+    pub show_acl: bool,                  // List ACL entries under each entry (--acl)
+    pub show_context: bool,              // Include the SELinux context column (-Z)
+    pub show_caps: bool,                 // Include the file capabilities column (--caps)
+    pub show_attrs: bool,                // Include the inode flags column in long format (--attrs)
+    pub show_links: bool,                // Include the hard link group column (--links)
+    pub ids: Rc<IdResolver>,             // Cached user and group name lookups for the run
+}
+
+impl FormatConfig {
+    /// Creates a display configuration with default columns and time handling.
+    /// `human_readable` switches both size columns to K, M, G units.
+    pub fn new(format: Format, human_readable: bool) -> Self {
+        let (size_unit, block_size) = if human_readable {
+            (BlockSize::Human(1024), BlockSize::Human(1024))
+        } else {
+            (BlockSize::bytes(), BlockSize::kibibytes())
+        };
+        FormatConfig {
+            format,
+            size_unit,
+            block_size,
+            show_inode: false,
+            show_blocks: false,
+            numeric_ids: false,
+            show_owner: true,
+            show_group: true,
+            indicator_style: IndicatorStyle::None,
+            quoting_style: QuotingStyle::Literal,
+            hide_control: false,
+            full_paths: false,
+            time_style: TimeStyle::default(),
+            time_field: TimeField::default(),
+            color_age: false,
+            now: SystemTime::now(),
+            line_width: 80,
+            max_name_width: None,
+            show_xattrs: false,
+            xattr_preview: false,
+            show_acl: false,
+            show_context: false,
+            show_caps: false,
+            show_attrs: false,
+            show_links: false,
+            ids: Rc::new(IdResolver::new(IdSource::default())),
+        }
+    }
+
+    /// Quotes a name or path using the configured quoting style
+    pub fn quote(&self, name: &OsStr) -> String {
+        quote_name(name, self.quoting_style, self.hide_control)
+    }
+
+    /// Resolves a uid to a user name, or the number itself with -n or
+    /// when the user is unknown
+    pub fn user_name(&self, uid: u32) -> String {
+        if self.numeric_ids {
+            return uid.to_string();
+        }
+        self.ids.user_name(uid)
+    }
+
+    /// Resolves a gid to a group name, or the number itself with -n or
+    /// when the group is unknown
+    pub fn group_name(&self, gid: u32) -> String {
+        if self.numeric_ids {
+            return gid.to_string();
+        }
+        self.ids.group_name(gid)
+    }
+}
+
+/// Widths of the variable long format columns, measured per listing so
+/// that every row lines up. Widths are in terminal columns, not bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColumnWidths {
+    pub nlink: usize,   // Hard link count (right-aligned)
+    pub owner: usize,   // Owner name (left-aligned)
+    pub group: usize,   // Group name (left-aligned)
+    pub size: usize,    // File size (right-aligned)
+    pub marker: usize,  // Marker after the permissions ('+', '.' or '@'), 0 if no entry has one
+    pub context: usize, // SELinux context (left-aligned), in every format with -Z
+    pub caps: usize,    // File capabilities (left-aligned), in every format with --caps
+    pub links: usize,   // Hard link group (left-aligned), in every format with --links
+}
+
+impl Default for ColumnWidths {
+    /// Minimum widths, used when rows are formatted on their own
+    fn default() -> Self {
+        ColumnWidths {
+            nlink: 3,
+            owner: 0,
+            group: 0,
+            size: 8,
+            marker: 0,
+            context: 0,
+            caps: 0,
+            links: 0,
+        }
+    }
+}
+
+impl ColumnWidths {
+    /// Measures the widest value of each column across a listing.
+    /// Outside long format only the context and capabilities columns are measured.
+    pub fn measure(entries: &[FileInfo], config: &FormatConfig) -> Self {
+        let mut widths = ColumnWidths::default();
+        for file_info in entries {
+            let formatter = FileInfoFormatter {
+                file_info,
+                config,
+                widths,
+            };
+            if config.show_context {
+                widths.context = widths.context.max(display_width(&formatter.context()));
+            }
+            if config.show_caps {
+                widths.caps = widths.caps.max(display_width(&formatter.caps()));
+            }
+            if config.show_links {
+                widths.links = widths.links.max(display_width(&formatter.link_group()));
+            }
+            if config.format != Format::Long {
+                continue;
+            }
+            widths.nlink = widths.nlink.max(display_width(&formatter.nlink()));
+            widths.owner = widths.owner.max(display_width(&formatter.owner_name()));
+            widths.group = widths.group.max(display_width(&formatter.group_name()));
+            widths.size = widths.size.max(display_width(&formatter.size()));
+            if formatter.marker().is_some() {
+                widths.marker = 1;
+            }
+        }
+        widths
+    }
 }
 
 /// Formatter for displaying FileInfo in various formats.
 /// Implements Display trait for easy rendering and testing.
 pub struct FileInfoFormatter<'a> {
     pub file_info: &'a FileInfo, // Reference to the file information to display
-    pub format: Format,          // The format to use for display
-    pub human_readable: bool,    // Whether to use human-readable sizes (K, M, G)
+    pub config: &'a FormatConfig, // Run-wide display settings
+    pub widths: ColumnWidths,    // Long format column widths for this listing
 }
 
 impl fmt::Display for FileInfoFormatter<'_> {
     /// Formats the FileInfo according to the selected format.
     /// This allows using the formatter with println!, format!, etc.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.format {
-            Format::Name => {
-                let colored_name =
-                    colorize_name(&self.file_info.file_name, &self.file_info.metadata);
-                write!(f, "{colored_name}")
-            }
-            Format::WithSize => {
-                let size = if self.human_readable {
-                    format_size_human(self.file_info.size())
-                } else {
-                    format_block_size(&self.file_info.metadata)
-                };
-                let colored_name =
-                    colorize_name(&self.file_info.file_name, &self.file_info.metadata);
-                write!(f, "{size} {colored_name}")
-            }
-            Format::Long => {
-                write!(f, "{}", self.format_long())
-            }
+        if self.config.show_inode {
+            write!(f, "{:>8} ", self.file_info.inode())?;
+        }
+        if self.config.show_blocks {
+            let size = match self.file_info.metadata() {
+                Some(_) => {
+                    format_block_size(self.file_info.blocks() * 512, &self.config.block_size)
+                }
+                None => format!("{:>8}", "?"),
+            };
+            write!(f, "{size} ")?;
+        }
+        if self.config.show_context && self.config.format != Format::Long {
+            write!(f, "{} ", pad_right(&self.context(), self.widths.context))?;
+        }
+        if self.config.show_caps && self.config.format != Format::Long {
+            write!(f, "{} ", pad_right(&self.caps(), self.widths.caps))?;
+        }
+        if self.config.show_links && self.config.format != Format::Long {
+            write!(f, "{} ", pad_right(&self.link_group(), self.widths.links))?;
+        }
+
+        match self.config.format {
+            Format::Long => write!(f, "{}", self.format_long()),
+            _ => write!(f, "{}", self.display_name()),
         }
     }
 }
@@ -58,32 +238,266 @@ impl FileInfoFormatter<'_> {
     /// Formats file information in long format (similar to ls -l).
     /// Includes permissions, links, owner, group, size, date, and name.
     /// For symlinks, also shows the target path.
+    /// Files whose metadata can't be read show '?' in every column, like GNU ls.
     fn format_long(&self) -> String {
-        let permissions = format_permissions(&self.file_info.metadata);
-        let nlink = self.file_info.nlink();
-        let owner = get_user_by_uid(self.file_info.uid()).map_or_else(
-            || self.file_info.uid().to_string(),
-            |u| u.name().to_string_lossy().to_string(),
+        let permissions = self.file_info.metadata().map_or_else(
+            || format!("{}?????????", file_type_char(self.file_info.file_type())),
+            format_permissions,
         );
-        let group = get_group_by_gid(self.file_info.gid()).map_or_else(
-            || self.file_info.gid().to_string(),
-            |g| g.name().to_string_lossy().to_string(),
-        );
-        let size = if self.human_readable {
-            format_size_human(self.file_info.size())
+        let widths = self.widths;
+        let nlink = pad_left(&self.nlink(), widths.nlink);
+        let size = pad_left(&self.size(), widths.size);
+        let modified = self.format_timestamp();
+
+        // If it's a symlink, show the target, classified by what it points to
+        let display_name = if let Some(target) = self.file_info.symlink_target() {
+            let name = self.colorize(&self.quoted_name());
+            let target_indicator = self.file_info.target_metadata().and_then(|m| {
+                indicator(
+                    m.file_type(),
+                    || m.permissions().mode(),
+                    self.config.indicator_style,
+                )
+            });
+            format!(
+                "{name} -> {}{}",
+                self.config.quote(target.as_os_str()),
+                target_indicator.map(String::from).unwrap_or_default()
+            )
+        } else {
+            self.display_name()
+        };
+
+        let marker = match self.marker() {
+            Some(marker) => marker.to_string(),
+            None => " ".repeat(widths.marker),
+        };
+
+        let mut line = format!("{permissions}{marker} {nlink} ");
+        if self.config.show_links {
+            line.push_str(&pad_right(&self.link_group(), widths.links));
+            line.push(' ');
+        }
+        if self.config.show_owner {
+            line.push_str(&pad_right(&self.owner_name(), widths.owner));
+            line.push(' ');
+        }
+        if self.config.show_group {
+            line.push_str(&pad_right(&self.group_name(), widths.group));
+            line.push(' ');
+        }
+        if self.config.show_context {
+            line.push_str(&pad_right(&self.context(), widths.context));
+            line.push(' ');
+        }
+        if self.config.show_caps {
+            line.push_str(&pad_right(&self.caps(), widths.caps));
+            line.push(' ');
+        }
+        if self.config.show_attrs {
+            line.push_str(&attrs::format(self.file_info.inode_flags()));
+            line.push(' ');
+        }
+        line.push_str(&format!("{size} {modified} {display_name}"));
+        line
+    }
+
+    /// Returns the character shown after the permissions in long format:
+    /// '+' when the file has an ACL, otherwise '.' when it has an SELinux
+    /// context, otherwise '@' when it has other extended attributes
+    fn marker(&self) -> Option<char> {
+        if self.file_info.has_acl() {
+            Some('+')
+        } else if self.file_info.security_context().is_some() {
+            Some('.')
+        } else if self.file_info.xattr_names().is_empty() {
+            None
         } else {
-            self.file_info.size().to_string()
+            Some('@')
+        }
+    }
+
+    /// Lists the file's extended attributes, one line each, for -@:
+    /// name, value size in bytes, and optionally a preview of the value
+    pub fn xattr_lines(&self) -> Vec<String> {
+        self.file_info
+            .xattr_names()
+            .iter()
+            .map(|name| {
+                let value = self.file_info.xattr_value(name).unwrap_or_default();
+                let mut line = format!("\t{}\t{:>6}", self.config.quote(name), value.len());
+                if self.config.xattr_preview {
+                    line.push_str("  ");
+                    line.push_str(&xattr::preview(&value));
+                }
+                line
+            })
+            .collect()
+    }
+
+    /// Lists the file's ACL entries, one line each, for --acl, in getfacl
+    /// notation (e.g., "user:alice:rwx"). Default ACL entries of a
+    /// directory are prefixed with "default:".
+    pub fn acl_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (attribute, prefix) in [(acl::ACCESS, ""), (acl::DEFAULT, "default:")] {
+            let Some(value) = self.file_info.xattr_value(OsStr::new(attribute)) else {
+                continue;
+            };
+            match acl::parse(&value) {
+                Some(entries) => lines.extend(
+                    entries
+                        .iter()
+                        .map(|entry| format!("\t{prefix}{}", self.acl_entry(entry))),
+                ),
+                None => lines.push(format!("\t{prefix}(unreadable ACL)")),
+            }
+        }
+        lines
+    }
+
+    /// Formats one ACL entry, resolving named users and groups
+    fn acl_entry(&self, entry: &AclEntry) -> String {
+        let (kind, qualifier) = match entry.tag {
+            Tag::UserObj => ("user", String::new()),
+            Tag::User(uid) => ("user", self.config.user_name(uid)),
+            Tag::GroupObj => ("group", String::new()),
+            Tag::Group(gid) => ("group", self.config.group_name(gid)),
+            Tag::Mask => ("mask", String::new()),
+            Tag::Other => ("other", String::new()),
         };
-        let modified = format_time(self.file_info.modified().unwrap_or(SystemTime::UNIX_EPOCH));
+        format!("{kind}:{qualifier}:{}", entry.perms_string())
+    }
 
-        let mut display_name = colorize_name(&self.file_info.file_name, &self.file_info.metadata);
+    /// Returns the SELinux context for the -Z column, or "?" if there is none
+    fn context(&self) -> String {
+        self.file_info.security_context().unwrap_or("?").to_string()
+    }
+
+    /// Returns the file capabilities for the --caps column, or "-" if there are none
+    fn caps(&self) -> String {
+        self.file_info
+            .capabilities()
+            .map_or_else(|| "-".to_string(), |caps| caps.to_text())
+    }
 
-        // If it's a symlink, show the target
-        if let Some(target) = self.file_info.symlink_target() {
-            display_name = format!("{} -> {}", display_name, target.display()).into();
+    /// Returns the hard link group for the --links column (e.g., "#1"),
+    /// or "-" if no other listed entry shares the inode
+    fn link_group(&self) -> String {
+        self.file_info
+            .link_group()
+            .map_or_else(|| "-".to_string(), |group| format!("#{group}"))
+    }
+
+    /// Formats the hard link count, or "?" if the metadata can't be read
+    fn nlink(&self) -> String {
+        match self.file_info.metadata() {
+            Some(_) => self.file_info.nlink().to_string(),
+            None => "?".to_string(),
         }
+    }
 
-        format!("{permissions} {nlink:>3} {owner} {group} {size:>8} {modified} {display_name}")
+    /// Formats the file size in the long format size unit
+    fn size(&self) -> String {
+        match self.file_info.metadata() {
+            Some(_) => format_size(self.file_info.size(), &self.config.size_unit),
+            None => "?".to_string(),
+        }
+    }
+
+    /// Returns the colored file name followed by its type indicator, if any.
+    /// In grid formats, names wider than --max-name-width are truncated.
+    fn display_name(&self) -> String {
+        let quoted = match self.config.max_name_width {
+            Some(max_width) if matches!(self.config.format, Format::Vertical | Format::Across) => {
+                quote_name_within(
+                    self.raw_display_name(),
+                    self.config.quoting_style,
+                    self.config.hide_control,
+                    max_width,
+                )
+            }
+            _ => self.quoted_name(),
+        };
+        let name = self.colorize(&quoted);
+        let indicator = indicator(
+            self.file_info.file_type(),
+            || self.file_info.mode(),
+            self.config.indicator_style,
+        );
+        match indicator {
+            Some(c) => format!("{name}{c}"),
+            None => name.to_string(),
+        }
+    }
+
+    /// Colors a name by file type, or with the capability color for regular
+    /// files that carry capabilities. Nothing is looked up (neither the mode
+    /// nor capabilities) unless color is actually being written.
+    fn colorize(&self, name: &str) -> ColoredString {
+        if !SHOULD_COLORIZE.should_colorize() {
+            return name.normal();
+        }
+        let file_type = self.file_info.file_type();
+        if file_type.is_file() && self.file_info.capabilities().is_some() {
+            colorize_capability(name)
+        } else {
+            colorize_name(name, file_type, || self.file_info.mode())
+        }
+    }
+
+    /// Returns the file name (or full path) quoted in the configured style
+    fn quoted_name(&self) -> String {
+        self.config.quote(self.raw_display_name())
+    }
+
+    /// Returns the unquoted name shown for the entry: its full path with
+    /// --zero listings of several directories, otherwise its name
+    fn raw_display_name(&self) -> &OsStr {
+        if self.config.full_paths {
+            self.file_info.path.as_os_str()
+        } else {
+            &self.file_info.raw_name
+        }
+    }
+
+    /// Resolves the owner name, falling back to the uid when unknown or when -n is set
+    fn owner_name(&self) -> String {
+        match self.file_info.metadata() {
+            Some(_) => self.config.user_name(self.file_info.uid()),
+            None => "?".to_string(),
+        }
+    }
+
+    /// Resolves the group name, falling back to the gid when unknown or when -n is set
+    fn group_name(&self) -> String {
+        match self.file_info.metadata() {
+            Some(_) => self.config.group_name(self.file_info.gid()),
+            None => "?".to_string(),
+        }
+    }
+
+    /// Formats the selected timestamp (--time-field) in the configured style,
+    /// padded to a fixed width and optionally shaded by age.
+    /// A timestamp the file system doesn't record is shown as "-".
+    fn format_timestamp(&self) -> String {
+        let config = self.config;
+        let Some(time) = self.file_info.time(config.time_field) else {
+            let missing = if self.file_info.metadata().is_some() {
+                "-"
+            } else {
+                "?"
+            };
+            return pad_right(missing, config.time_style.width());
+        };
+        let text = format_time(time, config.now, config.time_style);
+        let padded = pad_right(&text, config.time_style.width());
+
+        if config.color_age {
+            colorize_time(&padded, age_bucket(time, config.now)).to_string()
+        } else {
+            padded
+        }
     }
 }
 
@@ -100,10 +514,11 @@ mod tests {
         File::create(&file_path).unwrap();
 
         let file_info = FileInfo::from_path(&file_path).unwrap();
+        let config = FormatConfig::new(Format::SingleColumn, false);
         let formatter = FileInfoFormatter {
             file_info: &file_info,
-            format: Format::Name,
-            human_readable: false,
+            config: &config,
+            widths: ColumnWidths::default(),
         };
 
         let output = format!("{}", formatter);
@@ -117,10 +532,12 @@ mod tests {
         fs::write(&file_path, "Hello, World!").unwrap();
 
         let file_info = FileInfo::from_path(&file_path).unwrap();
+        let mut config = FormatConfig::new(Format::SingleColumn, true);
+        config.show_blocks = true;
         let formatter = FileInfoFormatter {
             file_info: &file_info,
-            format: Format::WithSize,
-            human_readable: true,
+            config: &config,
+            widths: ColumnWidths::default(),
         };
 
         let output = format!("{}", formatter);
@@ -136,10 +553,11 @@ mod tests {
         File::create(&file_path).unwrap();
 
         let file_info = FileInfo::from_path(&file_path).unwrap();
+        let config = FormatConfig::new(Format::Long, false);
         let formatter = FileInfoFormatter {
             file_info: &file_info,
-            format: Format::Long,
-            human_readable: false,
+            config: &config,
+            widths: ColumnWidths::default(),
         };
 
         let output = format!("{}", formatter);
@@ -147,4 +565,23 @@ mod tests {
         assert!(output.starts_with("-") || output.starts_with("d") || output.starts_with("l"));
         assert!(output.contains("test.txt"));
     }
+
+    #[test]
+    fn test_format_long_relative_time() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("test.txt");
+        File::create(&file_path).unwrap();
+
+        let file_info = FileInfo::from_path(&file_path).unwrap();
+        let mut config = FormatConfig::new(Format::Long, false);
+        config.time_style = TimeStyle::Relative;
+        let formatter = FileInfoFormatter {
+            file_info: &file_info,
+            config: &config,
+            widths: ColumnWidths::default(),
+        };
+
+        let output = format!("{}", formatter);
+        assert!(output.contains("just now"));
+    }
 }
@@ -0,0 +1,132 @@
+//! User and group name resolution.
+//!
+//! Looking up a name through NSS can mean a network round trip (LDAP, SSSD)
+//! per call, so every id is resolved at most once per run and the result is
+//! cached. `--id-source` can also bypass NSS: `files` reads `/etc/passwd` and
+//! `/etc/group` directly, and `none` never resolves names at all.
+
+use std::cell::{OnceCell, RefCell};
+use std::collections::HashMap;
+use std::fs;
+use users::{get_group_by_gid, get_user_by_uid};
+
+/// Where user and group names come from, as selected by `--id-source`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum IdSource {
+    #[default]
+    Nss,   // The system's name service (getpwuid/getgrgid)
+    Files, // /etc/passwd and /etc/group only
+    None,  // Numeric ids only
+}
+
+/// Resolves uids and gids to names, caching every answer for the run
+pub struct IdResolver {
+    source: IdSource,                           // Where names are looked up
+    users: RefCell<HashMap<u32, String>>,       // Resolved uids
+    groups: RefCell<HashMap<u32, String>>,      // Resolved gids
+    passwd: OnceCell<HashMap<u32, String>>,     // /etc/passwd, read on first use (files)
+    group_file: OnceCell<HashMap<u32, String>>, // /etc/group, read on first use (files)
+}
+
+impl IdResolver {
+    /// Creates a resolver with empty caches
+    pub fn new(source: IdSource) -> Self {
+        IdResolver {
+            source,
+            users: RefCell::new(HashMap::new()),
+            groups: RefCell::new(HashMap::new()),
+            passwd: OnceCell::new(),
+            group_file: OnceCell::new(),
+        }
+    }
+
+    /// Returns the user name for a uid, or the uid itself if it has none
+    pub fn user_name(&self, uid: u32) -> String {
+        cached(&self.users, uid, || match self.source {
+            IdSource::Nss => get_user_by_uid(uid).map(|u| u.name().to_string_lossy().to_string()),
+            IdSource::Files => self
+                .passwd
+                .get_or_init(|| read_id_file("/etc/passwd"))
+                .get(&uid)
+                .cloned(),
+            IdSource::None => None,
+        })
+    }
+
+    /// Returns the group name for a gid, or the gid itself if it has none
+    pub fn group_name(&self, gid: u32) -> String {
+        cached(&self.groups, gid, || match self.source {
+            IdSource::Nss => get_group_by_gid(gid).map(|g| g.name().to_string_lossy().to_string()),
+            IdSource::Files => self
+                .group_file
+                .get_or_init(|| read_id_file("/etc/group"))
+                .get(&gid)
+                .cloned(),
+            IdSource::None => None,
+        })
+    }
+}
+
+/// Looks an id up in a cache, resolving and storing it on a miss.
+/// Unknown ids are cached as their number so they are not looked up again.
+fn cached(
+    cache: &RefCell<HashMap<u32, String>>,
+    id: u32,
+    lookup: impl FnOnce() -> Option<String>,
+) -> String {
+    if let Some(name) = cache.borrow().get(&id) {
+        return name.clone();
+    }
+    let name = lookup().unwrap_or_else(|| id.to_string());
+    cache.borrow_mut().insert(id, name.clone());
+    name
+}
+
+/// Reads an `/etc/passwd`-style file into an id to name map.
+/// The name is the first field and the id the third; the first entry wins
+/// for duplicate ids, as with getpwuid. A missing file gives an empty map.
+fn read_id_file(path: &str) -> HashMap<u32, String> {
+    parse_id_file(&fs::read_to_string(path).unwrap_or_default())
+}
+
+/// Parses the contents of an `/etc/passwd` or `/etc/group` file
+fn parse_id_file(contents: &str) -> HashMap<u32, String> {
+    let mut ids = HashMap::new();
+    for line in contents.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split(':');
+        let (Some(name), Some(_), Some(id)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        if let Ok(id) = id.parse() {
+            ids.entry(id).or_insert_with(|| name.to_string());
+        }
+    }
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_id_file() {
+        let ids = parse_id_file(
+            "# comment\nroot:x:0:0:root:/root:/bin/bash\nalice:x:1000:1000::/home/alice:/bin/sh\n\
+             toor:x:0:0::/:/bin/sh\nbroken line\n",
+        );
+        assert_eq!(ids.get(&0).map(String::as_str), Some("root"));
+        assert_eq!(ids.get(&1000).map(String::as_str), Some("alice"));
+        assert_eq!(ids.len(), 2);
+    }
+
+    #[test]
+    fn test_numeric_source_and_cache() {
+        let resolver = IdResolver::new(IdSource::None);
+        assert_eq!(resolver.user_name(0), "0");
+        assert_eq!(resolver.group_name(42), "42");
+        assert_eq!(resolver.users.borrow().len(), 1);
+    }
+}
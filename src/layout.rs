@@ -0,0 +1,146 @@
+//! Multi-entry layouts: column grids and comma-separated streams.
+//!
+//! Entries are rendered to cells first; this module only decides where each
+//! cell goes, so it works on plain strings with precomputed display widths.
+
+/// Spaces between grid columns
+const COLUMN_GAP: usize = 2;
+
+/// A rendered entry and the number of terminal columns it occupies
+pub struct Cell {
+    pub text: String, // Rendered text, possibly containing color codes
+    pub width: usize, // Display width, excluding color codes
+}
+
+/// Arranges cells into as many columns as fit within `line_width`.
+/// Cells fill each column top to bottom (-C), or each row left to right
+/// when `across` is set (-x).
+pub fn grid(cells: &[Cell], line_width: usize, across: bool) -> Vec<String> {
+    if cells.is_empty() {
+        return Vec::new();
+    }
+
+    // Try the widest layout first, falling back to a single column
+    let max_columns = (line_width / (1 + COLUMN_GAP)).clamp(1, cells.len());
+    let (rows, widths) = (2..=max_columns)
+        .rev()
+        .map(|columns| {
+            let rows = cells.len().div_ceil(columns);
+            (rows, column_widths(cells, rows, across))
+        })
+        .find(|(_, widths)| {
+            widths.iter().sum::<usize>() + COLUMN_GAP * (widths.len() - 1) <= line_width
+        })
+        .unwrap_or_else(|| (cells.len(), column_widths(cells, cells.len(), across)));
+
+    let columns = widths.len();
+    (0..rows)
+        .map(|row| {
+            let row_cells: Vec<(usize, &Cell)> = (0..columns)
+                .filter_map(|column| {
+                    let index = if across {
+                        row * columns + column
+                    } else {
+                        column * rows + row
+                    };
+                    cells.get(index).map(|cell| (column, cell))
+                })
+                .collect();
+
+            let mut line = String::new();
+            for (position, (column, cell)) in row_cells.iter().enumerate() {
+                line.push_str(&cell.text);
+                // Pad every cell but the last on the line
+                if position + 1 < row_cells.len() {
+                    line.push_str(&" ".repeat(widths[*column] - cell.width + COLUMN_GAP));
+                }
+            }
+            line
+        })
+        .collect()
+}
+
+/// Computes the width of each column when cells are laid out in `rows` rows
+fn column_widths(cells: &[Cell], rows: usize, across: bool) -> Vec<usize> {
+    let columns = cells.len().div_ceil(rows);
+    let mut widths = vec![0; columns];
+    for (index, cell) in cells.iter().enumerate() {
+        let column = if across {
+            index % columns
+        } else {
+            index / rows
+        };
+        widths[column] = widths[column].max(cell.width);
+    }
+    widths
+}
+
+/// Joins cells with ", ", starting a new line whenever the next cell
+/// would run past `line_width` (-m)
+pub fn commas(cells: &[Cell], line_width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut used = 0;
+
+    for (index, cell) in cells.iter().enumerate() {
+        // Each cell but the last is followed by a comma
+        let trailer = usize::from(index + 1 < cells.len());
+        if used > 0 && used + 1 + cell.width + trailer > line_width {
+            lines.push(std::mem::take(&mut line));
+            used = 0;
+        }
+        if used > 0 {
+            line.push(' ');
+            used += 1;
+        }
+        line.push_str(&cell.text);
+        used += cell.width;
+        if trailer == 1 {
+            line.push(',');
+            used += 1;
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(names: &[&str]) -> Vec<Cell> {
+        names
+            .iter()
+            .map(|name| Cell {
+                text: (*name).to_string(),
+                width: name.len(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_grid_vertical() {
+        let names = cells(&["a", "bbb", "c", "dd", "e"]);
+        assert_eq!(grid(&names, 12, false), vec!["a    c   e", "bbb  dd"]);
+    }
+
+    #[test]
+    fn test_grid_across() {
+        let names = cells(&["a", "bbb", "c", "dd", "e"]);
+        assert_eq!(grid(&names, 12, true), vec!["a   bbb  c", "dd  e"]);
+    }
+
+    #[test]
+    fn test_grid_single_column_when_too_wide() {
+        let names = cells(&["long-name", "other-name"]);
+        assert_eq!(grid(&names, 5, false), vec!["long-name", "other-name"]);
+    }
+
+    #[test]
+    fn test_commas_wrap() {
+        let names = cells(&["alpha", "beta", "gamma", "delta"]);
+        assert_eq!(commas(&names, 14), vec!["alpha, beta,", "gamma, delta"]);
+    }
+}
@@ -0,0 +1,97 @@
+//! Hard link grouping for `--links`.
+//!
+//! Entries of one listing that share an inode (the same device and inode
+//! number) get a group number in listing order, shown as "#1", "#2", ...
+//! With `--links=groups`, a whole tree is walked instead and each inode with
+//! several links is printed once with every path found for it.
+
+use crate::file_info::FileInfo;
+use std::collections::HashMap;
+use std::os::unix::fs::MetadataExt;
+
+/// What --links shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LinksMode {
+    Annotate, // Mark entries that share an inode with a group number
+    Groups,   // List each multi-link inode in the tree once, with all its paths
+}
+
+/// Returns the (device, inode) pair of an entry that has other hard links.
+/// Directories are skipped: their link count comes from subdirectories.
+fn inode_key(file_info: &FileInfo) -> Option<(u64, u64)> {
+    let metadata = file_info.metadata()?;
+    (metadata.nlink() > 1 && !metadata.is_dir()).then(|| (metadata.dev(), metadata.ino()))
+}
+
+/// Numbers the groups of entries that share an inode, in listing order.
+/// Entries whose inode appears only once in the listing are left unmarked.
+pub fn assign_groups(entries: &[FileInfo]) {
+    let mut counts: HashMap<(u64, u64), usize> = HashMap::new();
+    for key in entries.iter().filter_map(inode_key) {
+        *counts.entry(key).or_default() += 1;
+    }
+
+    let mut groups = HashMap::new();
+    for entry in entries {
+        let Some(key) = inode_key(entry).filter(|key| counts[key] > 1) else {
+            continue;
+        };
+        let next = groups.len() + 1;
+        entry.set_link_group(*groups.entry(key).or_insert(next));
+    }
+}
+
+/// Gathers entries with several hard links by inode, keeping the order in
+/// which each inode is first seen and the order of paths within a group.
+/// Inodes with a single path here are kept too, since their other links
+/// lie outside the listing.
+pub fn group_by_inode(entries: Vec<FileInfo>) -> Vec<Vec<FileInfo>> {
+    let mut index: HashMap<(u64, u64), usize> = HashMap::new();
+    let mut groups: Vec<Vec<FileInfo>> = Vec::new();
+    for entry in entries {
+        let Some(key) = inode_key(&entry) else {
+            continue;
+        };
+        match index.get(&key) {
+            Some(&i) => groups[i].push(entry),
+            None => {
+                index.insert(key, groups.len());
+                groups.push(vec![entry]);
+            }
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_groups() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = |name| dir.path().join(name);
+        fs::write(path("a"), "a").unwrap();
+        fs::hard_link(path("a"), path("b")).unwrap();
+        fs::write(path("c"), "c").unwrap();
+        fs::write(path("d"), "d").unwrap();
+        fs::hard_link(path("d"), path("e")).unwrap();
+        fs::hard_link(path("d"), path("outside")).unwrap();
+
+        let entries: Vec<FileInfo> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|name| FileInfo::from_path(&path(name)).unwrap())
+            .collect();
+        assign_groups(&entries);
+        let groups: Vec<_> = entries.iter().map(FileInfo::link_group).collect();
+        assert_eq!(groups, [Some(1), Some(1), None, Some(2), Some(2)]);
+
+        let grouped = group_by_inode(entries);
+        let names: Vec<Vec<&str>> = grouped
+            .iter()
+            .map(|group| group.iter().map(|e| e.file_name.as_str()).collect())
+            .collect();
+        assert_eq!(names, [vec!["a", "b"], vec!["d", "e"]]);
+    }
+}
@@ -1,18 +1,50 @@
 //! Main function - program entrypoint
 
+mod acl;
+mod attrs;
+mod caps;
+mod colors;
 mod directory;
+mod duplicates;
 mod file_info;
+mod filter;
 mod formatter;
+mod ids;
+mod layout;
+mod links;
+mod output;
+mod query;
+mod quoting;
+mod report;
 mod sorting;
+mod statx;
+mod summary;
 mod utils;
+mod xattr;
 
-use clap::Parser;
-use directory::{collect_entries, get_subdirectories};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use directory::{
+    collect_entries, collect_tree, get_subdirectories, read_entries, TreeSizes, WalkOrder,
+};
+use duplicates::Duplicates;
 use file_info::FileInfo;
-use formatter::{FileInfoFormatter, Format};
-use sorting::{sort_directories, sort_entries, SortConfig};
-use std::io;
+use filter::{AgeTest, Filter, NamePattern, PermTest, SizeOption, Test, TypeSet};
+use formatter::{Format, FormatConfig};
+use ids::{IdResolver, IdSource};
+use links::LinksMode;
+use output::{report_unreadable, Output};
+use query::Query;
+use quoting::QuotingStyle;
+use report::{Report, ReportFormat};
+use sorting::{sort_directories, sort_entries, SortConfig, SortKey};
+use std::collections::VecDeque;
+use std::io::{self, IsTerminal};
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+use summary::{plural, Summary};
+use utils::{format_size, terminal_width, BlockSize, IndicatorStyle, TimeField, TimeStyle};
 
 #[derive(Parser, Debug)]
 #[command(name = "ls")]
@@ -20,30 +52,517 @@ use std::path::{Path, PathBuf};
 #[command(disable_help_flag = true)]
 #[allow(clippy::struct_excessive_bools)]
 pub struct Args {
-    #[arg(short = 'l', long, help = "Use a long listing format")]
+    #[arg(
+        short = 'l',
+        long,
+        overrides_with_all = ["one", "commas", "across", "vertical", "format"],
+        help = "Use a long listing format"
+    )]
     pub long: bool,
 
     #[arg(short = 'a', long, help = "Show hidden files (starting with .)")]
     pub all: bool,
 
-    #[arg(short = 's', long, help = "Show file sizes")]
+    #[arg(short = 's', help = "Print the allocated size of each file, in blocks")]
     pub size: bool,
 
-    #[arg(short = 'h', long = "human-readable", help = "Human-readable sizes")]
+    #[arg(
+        long = "size",
+        value_name = "[+-]N[ckMGT]",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "",
+        allow_hyphen_values = true,
+        help = "Without a value, like -s; as --size=N, only list files larger (+N), smaller (-N) or exactly N bytes"
+    )]
+    pub size_options: Vec<SizeOption>,
+
+    #[arg(
+        long = "type",
+        value_name = "TYPES",
+        help = "Only list entries of these types: f, d, l, p, s, b, c (comma-separated)"
+    )]
+    pub file_types: Option<TypeSet>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_parser = filter::reference_time,
+        help = "Only list entries modified more recently than FILE"
+    )]
+    pub newer: Option<SystemTime>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_parser = filter::reference_time,
+        help = "Only list entries modified before FILE"
+    )]
+    pub older: Option<SystemTime>,
+
+    #[arg(
+        long,
+        value_name = "[+-]N[smhdw]",
+        allow_hyphen_values = true,
+        help = "Only list entries modified more (+N) or less (-N) than N days (or other units) ago"
+    )]
+    pub mtime: Vec<AgeTest>,
+
+    #[arg(
+        long,
+        value_name = "USER",
+        value_parser = filter::parse_user,
+        help = "Only list entries owned by USER (name or uid)"
+    )]
+    pub owner: Option<u32>,
+
+    #[arg(
+        long,
+        value_name = "GROUP",
+        value_parser = filter::parse_group,
+        help = "Only list entries whose group is GROUP (name or gid)"
+    )]
+    pub group: Option<u32>,
+
+    #[arg(
+        long = "where",
+        value_name = "EXPR",
+        help = "Only list entries matching EXPR, e.g. 'size > 1M and mtime < now - 30d'"
+    )]
+    pub where_expr: Option<Query>,
+
+    #[arg(
+        long = "match",
+        value_name = "GLOB",
+        value_parser = filter::glob_pattern,
+        help = "Only list entries whose name matches the shell pattern GLOB (repeatable)"
+    )]
+    pub match_globs: Vec<NamePattern>,
+
+    #[arg(
+        long = "imatch",
+        value_name = "GLOB",
+        value_parser = filter::iglob_pattern,
+        help = "Like --match, but ignore case"
+    )]
+    pub imatch_globs: Vec<NamePattern>,
+
+    #[arg(
+        long = "regex",
+        value_name = "RE",
+        value_parser = filter::regex_pattern,
+        help = "Only list entries whose name contains a match for the regular expression RE (repeatable)"
+    )]
+    pub match_regexes: Vec<NamePattern>,
+
+    #[arg(
+        long = "iregex",
+        value_name = "RE",
+        value_parser = filter::iregex_pattern,
+        help = "Like --regex, but ignore case"
+    )]
+    pub imatch_regexes: Vec<NamePattern>,
+
+    #[arg(
+        long = "exclude",
+        value_name = "GLOB",
+        value_parser = filter::glob_pattern,
+        help = "Don't list entries whose name matches the shell pattern GLOB (repeatable)"
+    )]
+    pub exclude_globs: Vec<NamePattern>,
+
+    #[arg(
+        long = "iexclude",
+        value_name = "GLOB",
+        value_parser = filter::iglob_pattern,
+        help = "Like --exclude, but ignore case"
+    )]
+    pub iexclude_globs: Vec<NamePattern>,
+
+    #[arg(
+        long = "exclude-regex",
+        value_name = "RE",
+        value_parser = filter::regex_pattern,
+        help = "Don't list entries whose name contains a match for RE (repeatable)"
+    )]
+    pub exclude_regexes: Vec<NamePattern>,
+
+    #[arg(
+        long = "iexclude-regex",
+        value_name = "RE",
+        value_parser = filter::iregex_pattern,
+        help = "Like --exclude-regex, but ignore case"
+    )]
+    pub iexclude_regexes: Vec<NamePattern>,
+
+    #[arg(
+        long = "full-path",
+        help = "Match name patterns against the path below the listed directory (e.g. 'src/*.rs')"
+    )]
+    pub full_path: bool,
+
+    #[arg(
+        long,
+        value_name = "MODE",
+        allow_hyphen_values = true,
+        help = "Only list entries with permissions MODE exactly, all of -MODE, or any of /MODE (octal or u+x style)"
+    )]
+    pub perm: Option<PermTest>,
+
+    #[arg(
+        short = 'h',
+        long = "human-readable",
+        overrides_with_all = ["si", "block_size"],
+        help = "Human-readable sizes"
+    )]
     pub human_readable: bool,
 
+    #[arg(
+        long,
+        overrides_with_all = ["human_readable", "block_size"],
+        help = "Like -h, but use powers of 1000 not 1024"
+    )]
+    pub si: bool,
+
+    #[arg(
+        long = "block-size",
+        value_name = "SIZE",
+        overrides_with_all = ["human_readable", "si"],
+        help = "Scale sizes by SIZE (e.g. K, MB, 1M)"
+    )]
+    pub block_size: Option<BlockSize>,
+
+    #[arg(
+        short = 'k',
+        long,
+        help = "Default to 1024-byte blocks for file system usage"
+    )]
+    pub kibibytes: bool,
+
+    #[arg(short = 'i', long, help = "Print the index number of each file")]
+    pub inode: bool,
+
+    #[arg(
+        short = 'n',
+        long = "numeric-uid-gid",
+        help = "Like -l, but list numeric user and group IDs"
+    )]
+    pub numeric_ids: bool,
+
+    #[arg(short = 'g', help = "Like -l, but do not list owner")]
+    pub no_owner: bool,
+
+    #[arg(short = 'o', help = "Like -l, but do not list group information")]
+    pub long_no_group: bool,
+
+    #[arg(
+        short = 'G',
+        long = "no-group",
+        help = "In a long listing, don't print group names"
+    )]
+    pub no_group: bool,
+
     #[arg(short = 'R', long, help = "List subdirectories recursively")]
     pub recursive: bool,
 
-    #[arg(short = 't', long, help = "Sort by modification time, newest first")]
-    pub time: bool,
+    #[arg(
+        long,
+        help = "List every entry below each directory as one sorted list of relative paths"
+    )]
+    pub flat: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "ORDER",
+        default_value_t = WalkOrder::Dfs,
+        help = "Order to visit directories with -R or --flat --stream (dfs, bfs)"
+    )]
+    pub walk: WalkOrder,
+
+    #[arg(
+        long,
+        help = "Print entries as they are read, without sorting or aligning columns"
+    )]
+    pub stream: bool,
+
+    #[arg(
+        short = 't',
+        long = "time",
+        overrides_with = "sort_size",
+        help = "Sort by time (see --time-field), newest first"
+    )]
+    pub sort_time: bool,
+
+    #[arg(
+        short = 'S',
+        overrides_with = "sort_time",
+        help = "Sort by size, largest first"
+    )]
+    pub sort_size: bool,
+
+    #[arg(
+        long,
+        help = "Print file counts, sizes and dates after each directory, and for the whole run"
+    )]
+    pub summary: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "text",
+        conflicts_with_all = ["duplicates", "links", "flat", "stream", "long", "one", "commas", "across", "vertical", "format"],
+        help = "Print a size histogram, extension breakdown and largest entries instead of a listing (text, json)"
+    )]
+    pub report: Option<ReportFormat>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 10,
+        help = "Number of largest files and directories in --report"
+    )]
+    pub top: usize,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "annotate",
+        conflicts_with = "stream",
+        help = "Mark entries sharing an inode with a group number, or list each multi-link inode in the tree with all its paths (annotate, groups)"
+    )]
+    pub links: Option<LinksMode>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "text",
+        conflicts_with_all = ["links", "flat", "stream", "long", "one", "commas", "across", "vertical", "format"],
+        help = "Print groups of byte-for-byte identical files below each path instead of a listing (text, json)"
+    )]
+    pub duplicates: Option<ReportFormat>,
+
+    #[arg(
+        long = "total-size",
+        help = "Show directory sizes as the total of their whole tree (like du)"
+    )]
+    pub total_size: bool,
+
+    #[arg(
+        long = "time-field",
+        value_enum,
+        value_name = "WORD",
+        default_value_t = TimeField::Mtime,
+        help = "Time to show in long format and sort by with -t"
+    )]
+    pub time_field: TimeField,
 
     #[arg(short = 'r', long, help = "Reverse order while sorting")]
     pub reverse: bool,
 
-    #[arg(short = '1', long = "one", help = "List one file per line")]
+    #[arg(
+        short = '1',
+        long = "one",
+        overrides_with_all = ["long", "commas", "across", "vertical", "format"],
+        help = "List one file per line"
+    )]
     pub one: bool,
 
+    #[arg(
+        short = 'm',
+        overrides_with_all = ["long", "one", "across", "vertical", "format"],
+        help = "Fill width with a comma separated list of entries"
+    )]
+    pub commas: bool,
+
+    #[arg(
+        short = 'x',
+        overrides_with_all = ["long", "one", "commas", "vertical", "format"],
+        help = "List entries by lines instead of by columns"
+    )]
+    pub across: bool,
+
+    #[arg(
+        short = 'C',
+        overrides_with_all = ["long", "one", "commas", "across", "format"],
+        help = "List entries by columns"
+    )]
+    pub vertical: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WORD",
+        overrides_with_all = ["long", "one", "commas", "across", "vertical"],
+        help = "Listing format (across, commas, horizontal, long, single-column, verbose, vertical)"
+    )]
+    pub format: Option<Format>,
+
+    #[arg(
+        short = 'w',
+        long,
+        value_name = "COLS",
+        help = "Set output width to COLS (0 means no limit)"
+    )]
+    pub width: Option<usize>,
+
+    #[arg(
+        short = '@',
+        long,
+        help = "List extended attribute names and value sizes under each entry"
+    )]
+    pub xattr: bool,
+
+    #[arg(
+        long = "xattr-preview",
+        help = "Like -@, but also preview each attribute value"
+    )]
+    pub xattr_preview: bool,
+
+    #[arg(
+        short = 'Z',
+        long,
+        help = "Print the SELinux security context of each file"
+    )]
+    pub context: bool,
+
+    #[arg(long, help = "Print the Linux capabilities of each file")]
+    pub caps: bool,
+
+    #[arg(
+        long,
+        help = "Show inode flags (immutable, append-only, ...) in long format"
+    )]
+    pub attrs: bool,
+
+    #[arg(long, help = "List POSIX ACL entries under each entry")]
+    pub acl: bool,
+
+    #[arg(
+        long = "max-name-width",
+        value_name = "COLS",
+        help = "In column formats, truncate names wider than COLS with an ellipsis (0 means no limit)"
+    )]
+    pub max_name_width: Option<usize>,
+
+    #[arg(
+        short = 'F',
+        long,
+        overrides_with_all = ["slash", "file_type", "indicator_style"],
+        help = "Append indicator (one of */=@|) to entries"
+    )]
+    pub classify: bool,
+
+    #[arg(
+        short = 'p',
+        overrides_with_all = ["classify", "file_type", "indicator_style"],
+        help = "Append / indicator to directories"
+    )]
+    pub slash: bool,
+
+    #[arg(
+        long = "file-type",
+        overrides_with_all = ["classify", "slash", "indicator_style"],
+        help = "Likewise, except do not append '*'"
+    )]
+    pub file_type: bool,
+
+    #[arg(
+        long = "indicator-style",
+        value_enum,
+        value_name = "STYLE",
+        overrides_with_all = ["classify", "slash", "file_type"],
+        help = "Append indicator with style STYLE to entry names"
+    )]
+    pub indicator_style: Option<IndicatorStyle>,
+
+    #[arg(
+        short = 'Q',
+        long = "quote-name",
+        overrides_with_all = ["escape", "literal", "quoting_style"],
+        help = "Enclose entry names in double quotes"
+    )]
+    pub quote_name: bool,
+
+    #[arg(
+        short = 'b',
+        long,
+        overrides_with_all = ["quote_name", "literal", "quoting_style"],
+        help = "Print C-style escapes for nongraphic characters"
+    )]
+    pub escape: bool,
+
+    #[arg(
+        short = 'N',
+        long,
+        overrides_with_all = ["quote_name", "escape", "quoting_style"],
+        help = "Print entry names without quoting"
+    )]
+    pub literal: bool,
+
+    #[arg(
+        long = "quoting-style",
+        value_enum,
+        value_name = "WORD",
+        overrides_with_all = ["quote_name", "escape", "literal"],
+        help = "Use quoting style WORD for entry names"
+    )]
+    pub quoting_style: Option<QuotingStyle>,
+
+    #[arg(
+        short = 'q',
+        long = "hide-control-chars",
+        overrides_with = "show_control_chars",
+        help = "Print ? instead of nongraphic characters"
+    )]
+    pub hide_control_chars: bool,
+
+    #[arg(
+        long = "show-control-chars",
+        overrides_with = "hide_control_chars",
+        help = "Show nongraphic characters as-is"
+    )]
+    pub show_control_chars: bool,
+
+    #[arg(
+        long = "id-source",
+        value_enum,
+        default_value_t = IdSource::Nss,
+        value_name = "SOURCE",
+        help = "Where to look up user and group names: nss, files (/etc/passwd, /etc/group) or none"
+    )]
+    pub id_source: IdSource,
+
+    #[arg(
+        long = "time-style",
+        value_enum,
+        default_value_t = TimeStyle::Locale,
+        value_name = "STYLE",
+        help = "Time display style in long format"
+    )]
+    pub time_style: TimeStyle,
+
+    #[arg(
+        long = "color-age",
+        help = "Shade dates by age (hour, day, week, month, older)"
+    )]
+    pub color_age: bool,
+
+    #[arg(
+        long,
+        help = "End each output entry with NUL, not newline (no color or quoting)"
+    )]
+    pub zero: bool,
+
     #[arg(long, help = "Print help", action = clap::ArgAction::Help)]
     pub help: Option<bool>,
 
@@ -66,15 +585,57 @@ impl Args {
         };
 
         let multiple_paths = paths.len() > 1;
+        let config = self.format_config(multiple_paths);
+        let mut output = Output::new(self.zero);
         let mut errors = Vec::new();
+        let mut run = Run {
+            filter: self.filter(Rc::clone(&config.ids)),
+            ..Run::default()
+        };
+        let mut broken_pipe = false;
+
+        if self.zero {
+            colored::control::set_override(false);
+        }
 
         for (index, path) in paths.iter().enumerate() {
-            if multiple_paths && index > 0 {
-                println!();
+            if multiple_paths && index > 0 && !self.zero && !self.json_output() {
+                output.separator()?;
             }
 
-            if let Err(e) = self.list_path(path, multiple_paths, 0) {
-                errors.push((path.clone(), e));
+            let result = match (self.report, self.duplicates) {
+                (Some(format), _) => {
+                    self.report_path(path, format, multiple_paths, &config, &mut output)
+                }
+                (None, Some(format)) => {
+                    self.duplicates_path(path, format, multiple_paths, &config, &mut output, &run)
+                }
+                _ if self.links == Some(LinksMode::Groups) => {
+                    self.link_groups(path, multiple_paths, &config, &mut output, &run)
+                }
+                _ if self.flat => {
+                    self.list_flat(path, multiple_paths, &config, &mut output, &mut run)
+                }
+                _ => self.list_path(path, multiple_paths, &config, &mut output, &mut run),
+            };
+            match result {
+                Ok(()) => {}
+                // The reader went away (e.g. `| head`), so stop quietly
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+                    broken_pipe = true;
+                    break;
+                }
+                Err(e) => errors.push((path.clone(), e)),
+            }
+        }
+
+        // Summarize the whole run when more than one listing was shown
+        if self.summary && (multiple_paths || self.recursive) && !broken_pipe {
+            let result = self.print_overall(&run.overall, &config, &mut output);
+            if let Err(e) = result {
+                if e.kind() != io::ErrorKind::BrokenPipe {
+                    return Err(e);
+                }
             }
         }
 
@@ -92,87 +653,570 @@ impl Args {
     /// # Arguments
     /// * `path` - The path to list
     /// * `show_path_header` - Whether to print the path name before listing
-    /// * `depth` - Current recursion depth (used for recursive listing)
-    fn list_path(&self, path: &Path, show_path_header: bool, depth: usize) -> io::Result<()> {
+    /// * `config` - Display settings shared across the run
+    /// * `output` - Where listing lines are written
+    /// * `run` - State shared by the run's listings
+    fn list_path(
+        &self,
+        path: &Path,
+        show_path_header: bool,
+        config: &FormatConfig,
+        output: &mut Output,
+        run: &mut Run,
+    ) -> io::Result<()> {
         if path.is_file() {
             let file_info = FileInfo::from_path(path)?;
-            self.display_file(&file_info);
-        } else {
-            if show_path_header || (self.recursive && depth > 0) {
-                println!("{}:", path.display());
+            output.entries(std::slice::from_ref(&file_info), config)?;
+            if self.summary {
+                run.overall.add(&file_info);
             }
+            return Ok(());
+        }
 
-            // Collect and sort entries
-            let mut entries = collect_entries(path, self.all)?;
-            let sort_config = SortConfig::new(self.time, self.reverse);
-            sort_entries(&mut entries, &sort_config);
+        // Directories still to be listed, in --walk order. Only paths are
+        // queued, so memory grows with the number of directories rather
+        // than the number of entries.
+        let mut pending = VecDeque::from([path.to_path_buf()]);
+        let mut root = true;
+        while let Some(dir) = pending.pop_front() {
+            if !root && !output.is_zero() {
+                output.separator()?;
+            }
+            let show_header = show_path_header || !root;
+            match self.list_directory(&dir, path, show_header, config, output, run) {
+                Ok(subdirs) => self.enqueue(&mut pending, subdirs),
+                Err(e) if root || e.kind() == io::ErrorKind::BrokenPipe => return Err(e),
+                Err(e) => eprintln!("ls: {}: {}", dir.display(), e),
+            }
+            root = false;
+        }
+        Ok(())
+    }
 
-            // Display total blocks for long format
-            if self.long && !entries.is_empty() {
-                let total = entries.iter().map(|f| f.blocks() * 512 / 1024).sum::<u64>();
-                println!("total {total}");
+    /// Lists one directory and returns the subdirectories to list after it
+    /// with -R. Directories that fail the filter are still descended into.
+    /// `root` is the path given on the command line that `dir` lies below.
+    fn list_directory(
+        &self,
+        dir: &Path,
+        root: &Path,
+        show_header: bool,
+        config: &FormatConfig,
+        output: &mut Output,
+        run: &mut Run,
+    ) -> io::Result<Vec<PathBuf>> {
+        // With --zero, entries carry full paths instead of headers
+        if show_header && !output.is_zero() {
+            output.header(&config.quote(dir.as_os_str()))?;
+        }
+
+        if self.stream {
+            let mut summary = Summary::default();
+            let subdirs = self.stream_directory(dir, root, config, output, &mut summary, run)?;
+            if self.summary {
+                print_summary(&summary, config, output, &mut run.overall)?;
             }
+            return Ok(subdirs);
+        }
+
+        // Collect, filter and sort entries
+        let (mut entries, skipped) = if self.recursive && !run.filter.is_empty() {
+            collect_entries(dir, self.all, &Filter::default(), root)?
+                .into_iter()
+                .partition(|entry| run.filter.matches(entry, root))
+        } else {
+            (
+                collect_entries(dir, self.all, &run.filter, root)?,
+                Vec::new(),
+            )
+        };
+        report_unreadable(&skipped);
+        self.show_entries(&mut entries, config, output, run)?;
+
+        if !self.recursive {
+            return Ok(Vec::new());
+        }
+        entries.extend(skipped);
+        let mut dirs = get_subdirectories(&entries);
 
-            // Display each entry
-            for file_info in &entries {
-                self.display_file(file_info);
+        // Sort directories for consistent output
+        sort_directories(&mut dirs);
+        Ok(dirs.into_iter().map(|dir| dir.path.clone()).collect())
+    }
+
+    /// Prints a directory's entries as they are read, without sorting
+    /// (--stream), and returns its subdirectories with -R or --flat.
+    /// `root` is the path given on the command line that `dir` lies below;
+    /// with --flat, entries are shown by their path below it.
+    fn stream_directory(
+        &self,
+        dir: &Path,
+        root: &Path,
+        config: &FormatConfig,
+        output: &mut Output,
+        summary: &mut Summary,
+        run: &mut Run,
+    ) -> io::Result<Vec<PathBuf>> {
+        let mut subdirs = Vec::new();
+        for entry in read_entries(dir, self.all)? {
+            let mut file_info = match entry {
+                Ok(file_info) => file_info,
+                Err(e) => {
+                    eprintln!("ls: {e}");
+                    continue;
+                }
+            };
+            let descend = self.recursive || self.flat;
+            if descend && file_info.is_dir() && !file_info.is_hidden() {
+                subdirs.push(file_info.path.clone());
+            }
+            if !run.filter.matches(&file_info, root) {
+                report_unreadable(std::slice::from_ref(&file_info));
+                continue;
+            }
+            if self.total_size && file_info.is_dir() {
+                file_info.set_tree_size(run.sizes.size(&file_info));
             }
+            if self.flat {
+                file_info.relative_to(root);
+            }
+            output.stream_entry(&file_info, config)?;
+            summary.add(&file_info);
+        }
+        Ok(subdirs)
+    }
 
-            // Handle recursive listing
-            if self.recursive {
-                self.list_subdirectories(&entries, depth)?;
+    /// Queues subdirectories for listing: next for depth-first order, or
+    /// after everything already queued for breadth-first order
+    fn enqueue(&self, pending: &mut VecDeque<PathBuf>, subdirs: Vec<PathBuf>) {
+        match self.walk {
+            WalkOrder::Dfs => {
+                for subdir in subdirs.into_iter().rev() {
+                    pending.push_front(subdir);
+                }
             }
+            WalkOrder::Bfs => pending.extend(subdirs),
         }
+    }
 
+    /// Lists everything below a directory as one list of relative paths,
+    /// sorted across the whole tree (--flat). With --stream, entries are
+    /// printed in --walk order as they are read instead.
+    fn list_flat(
+        &self,
+        path: &Path,
+        show_path_header: bool,
+        config: &FormatConfig,
+        output: &mut Output,
+        run: &mut Run,
+    ) -> io::Result<()> {
+        if path.is_file() {
+            return self.list_path(path, show_path_header, config, output, run);
+        }
+        if show_path_header && !output.is_zero() {
+            output.header(&config.quote(path.as_os_str()))?;
+        }
+        if !self.stream {
+            let mut entries = collect_tree(path, self.all, &run.filter)?;
+            return self.show_entries(&mut entries, config, output, run);
+        }
+
+        let mut summary = Summary::default();
+        let mut pending = VecDeque::from([path.to_path_buf()]);
+        let mut root = true;
+        while let Some(dir) = pending.pop_front() {
+            match self.stream_directory(&dir, path, config, output, &mut summary, run) {
+                Ok(subdirs) => self.enqueue(&mut pending, subdirs),
+                Err(e) if root || e.kind() == io::ErrorKind::BrokenPipe => return Err(e),
+                Err(e) => eprintln!("ls: {}: {}", dir.display(), e),
+            }
+            root = false;
+        }
+        if self.summary {
+            print_summary(&summary, config, output, &mut run.overall)?;
+        }
         Ok(())
     }
 
-    /// Recursively lists subdirectories
-    fn list_subdirectories(&self, entries: &[FileInfo], depth: usize) -> io::Result<()> {
-        let mut dirs = get_subdirectories(entries);
+    /// Sorts and displays the entries of one listing, with the total line
+    /// and, for --summary, the statistics line
+    fn show_entries(
+        &self,
+        entries: &mut [FileInfo],
+        config: &FormatConfig,
+        output: &mut Output,
+        run: &mut Run,
+    ) -> io::Result<()> {
+        if self.total_size {
+            for entry in entries.iter().filter(|entry| entry.is_dir()) {
+                entry.set_tree_size(run.sizes.size(entry));
+            }
+        }
+        let sort_config = SortConfig::new(self.sort_key(), self.time_field, self.reverse);
+        sort_entries(entries, &sort_config);
+        if config.show_links {
+            links::assign_groups(entries);
+        }
+
+        // Display total blocks for long format and -s
+        if (config.format == Format::Long || config.show_blocks) && !entries.is_empty() {
+            let total = entries.iter().map(|f| f.blocks() * 512).sum::<u64>();
+            output.line(format_args!(
+                "total {}",
+                format_size(total, &config.block_size).trim_start()
+            ))?;
+        }
 
-        // Sort directories for consistent output
-        sort_directories(&mut dirs);
+        // Display each entry
+        output.entries(entries, config)?;
 
-        for dir in dirs {
-            println!();
-            if let Err(e) = self.list_path(&dir.path, true, depth + 1) {
-                eprintln!("ls: {}: {}", dir.path.display(), e);
+        // Display the directory's statistics
+        if self.summary {
+            let mut summary = Summary::default();
+            for entry in &*entries {
+                summary.add(entry);
             }
+            print_summary(&summary, config, output, &mut run.overall)?;
         }
 
         Ok(())
     }
 
-    /// Displays a single file using the appropriate format.
-    /// Creates a FileInfoFormatter with the correct format and renders it.
-    fn display_file(&self, file_info: &FileInfo) {
-        let format = self.get_format();
-        let formatter = FileInfoFormatter {
-            file_info,
-            format,
-            human_readable: self.human_readable,
+    /// Prints the capacity report for one path (--report)
+    fn report_path(
+        &self,
+        path: &Path,
+        format: ReportFormat,
+        show_path_header: bool,
+        config: &FormatConfig,
+        output: &mut Output,
+    ) -> io::Result<()> {
+        let report = Report::build(path, self.all, self.recursive, self.top)?;
+        match format {
+            ReportFormat::Text => {
+                if show_path_header && !output.is_zero() {
+                    output.header(&config.quote(path.as_os_str()))?;
+                }
+                for line in report.to_text(&config.size_unit) {
+                    output.line(line)?;
+                }
+                Ok(())
+            }
+            ReportFormat::Json => output.line(report.to_json()),
+        }
+    }
+
+    /// Prints the groups of identical files below one path (--duplicates)
+    fn duplicates_path(
+        &self,
+        path: &Path,
+        format: ReportFormat,
+        show_path_header: bool,
+        config: &FormatConfig,
+        output: &mut Output,
+        run: &Run,
+    ) -> io::Result<()> {
+        let duplicates = Duplicates::find(path, self.all, &run.filter)?;
+        match format {
+            ReportFormat::Text => {
+                if show_path_header && !output.is_zero() {
+                    output.header(&config.quote(path.as_os_str()))?;
+                }
+                for line in duplicates.to_text(&config.size_unit) {
+                    output.line(line)?;
+                }
+                Ok(())
+            }
+            ReportFormat::Json => output.line(duplicates.to_json()),
+        }
+    }
+
+    /// Whether the run prints one JSON object per path instead of a listing
+    fn json_output(&self) -> bool {
+        [self.report, self.duplicates].contains(&Some(ReportFormat::Json))
+    }
+
+    /// Lists each inode with several hard links below a directory once,
+    /// under a header line, with every path found for it (--links=groups)
+    fn link_groups(
+        &self,
+        path: &Path,
+        show_path_header: bool,
+        config: &FormatConfig,
+        output: &mut Output,
+        run: &Run,
+    ) -> io::Result<()> {
+        if show_path_header && !output.is_zero() {
+            output.header(&config.quote(path.as_os_str()))?;
+        }
+        let mut entries = if path.is_file() {
+            vec![FileInfo::from_path(path)?]
+        } else {
+            collect_tree(path, self.all, &run.filter)?
         };
-        println!("{formatter}");
+        let sort_config = SortConfig::new(self.sort_key(), self.time_field, self.reverse);
+        sort_entries(&mut entries, &sort_config);
+
+        for (index, group) in links::group_by_inode(entries).iter().enumerate() {
+            if index > 0 && !output.is_zero() {
+                output.separator()?;
+            }
+            let first = &group[0];
+            let mut header = format!(
+                "inode {}, {}",
+                first.inode(),
+                plural(first.nlink(), "link", "links")
+            );
+            // Links outside the tree, hidden or filtered out are all unlisted
+            let unlisted = first.nlink().saturating_sub(group.len() as u64);
+            if unlisted > 0 {
+                header.push_str(&format!(" ({unlisted} not listed)"));
+            }
+            if !output.is_zero() {
+                output.header(&header)?;
+            }
+            output.entries(group, config)?;
+            // With --zero, an empty entry ends each group instead of the header
+            if output.is_zero() {
+                output.line("")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints the statistics of every listing in the run (--summary)
+    fn print_overall(
+        &self,
+        overall: &Summary,
+        config: &FormatConfig,
+        output: &mut Output,
+    ) -> io::Result<()> {
+        if !self.zero {
+            output.separator()?;
+        }
+        output.line(format_args!("overall: {}", overall.render(config)))
     }
 
-    /// Determines the display format based on command-line arguments.
-    /// Priority: -1 (one column) > -l (long) > -s (with size) > default (name only)
+    /// Returns the sort key selected by -t or -S (the last one given wins)
+    fn sort_key(&self) -> SortKey {
+        if self.sort_time {
+            SortKey::Time
+        } else if self.sort_size {
+            SortKey::Size
+        } else {
+            SortKey::Name
+        }
+    }
+
+    /// Builds the display settings for this run.
+    /// The current time is captured once so every row is dated against the same instant.
+    /// `multiple_paths` is needed because --zero prints full paths instead of headers.
+    fn format_config(&self, multiple_paths: bool) -> FormatConfig {
+        let mut config = FormatConfig {
+            show_inode: self.inode,
+            show_blocks: self.size || self.size_options.contains(&SizeOption::Blocks),
+            numeric_ids: self.numeric_ids,
+            show_owner: !self.no_owner,
+            show_group: !self.long_no_group && !self.no_group,
+            indicator_style: self.get_indicator_style(),
+            quoting_style: self.get_quoting_style(),
+            hide_control: !self.zero
+                && (self.hide_control_chars
+                    || (!self.show_control_chars && io::stdout().is_terminal())),
+            full_paths: self.zero && (self.recursive || multiple_paths),
+            time_style: self.time_style,
+            time_field: self.time_field,
+            color_age: self.color_age,
+            line_width: match self.width {
+                Some(0) => usize::MAX,
+                Some(width) => width,
+                None => terminal_width(),
+            },
+            max_name_width: self.max_name_width.filter(|&width| width > 0),
+            show_xattrs: self.xattr || self.xattr_preview,
+            xattr_preview: self.xattr_preview,
+            show_acl: self.acl,
+            show_context: self.context,
+            show_caps: self.caps,
+            show_attrs: self.attrs,
+            show_links: self.links == Some(LinksMode::Annotate),
+            ids: Rc::new(IdResolver::new(self.id_source)),
+            ..FormatConfig::new(self.get_format(), self.human_readable)
+        };
+
+        // --si, --block-size and the BLOCK_SIZE variables scale both size columns
+        let unit = if self.si {
+            Some(BlockSize::Human(1000))
+        } else if self.human_readable {
+            None
+        } else {
+            self.block_size.clone().or_else(block_size_from_env)
+        };
+        if let Some(unit) = unit {
+            config.size_unit = unit.clone();
+            config.block_size = unit;
+        }
+
+        // -k only resets block counts, and only when no explicit unit was given
+        if self.kibibytes && !self.human_readable && !self.si && self.block_size.is_none() {
+            config.block_size = BlockSize::kibibytes();
+        }
+
+        config
+    }
+
+    /// Collects the entry filters (--type, --size=N, --newer, ...) into one
+    /// Filter that entries must pass all of, resolving names with `ids`
+    fn filter(&self, ids: Rc<IdResolver>) -> Filter {
+        let mut tests = Vec::new();
+        tests.extend(self.file_types.clone().map(Test::Type));
+        tests.extend(self.size_options.iter().filter_map(|option| match option {
+            SizeOption::Test(size) => Some(Test::Size(*size)),
+            SizeOption::Blocks => None,
+        }));
+        tests.extend(self.newer.map(Test::Newer));
+        tests.extend(self.older.map(Test::Older));
+        tests.extend(self.mtime.iter().map(|age| Test::Age(*age)));
+        tests.extend(self.owner.map(Test::Owner));
+        tests.extend(self.group.map(Test::Group));
+        tests.extend(self.perm.map(Test::Perm));
+        tests.extend(self.where_expr.clone().map(Test::Where));
+
+        // Patterns of one kind are alternatives: a name is listed if it
+        // matches any --match or --regex, and dropped if it matches any
+        // --exclude or --exclude-regex
+        let includes: Vec<_> = [
+            &self.match_globs,
+            &self.imatch_globs,
+            &self.match_regexes,
+            &self.imatch_regexes,
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+        let excludes: Vec<_> = [
+            &self.exclude_globs,
+            &self.iexclude_globs,
+            &self.exclude_regexes,
+            &self.iexclude_regexes,
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+        if !includes.is_empty() {
+            tests.push(Test::Include(includes));
+        }
+        if !excludes.is_empty() {
+            tests.push(Test::Exclude(excludes));
+        }
+        Filter::new(tests, self.full_path, ids)
+    }
+
+    /// Determines which type indicators to append from -F, -p, --file-type
+    /// and --indicator-style (these override each other, last one wins)
+    fn get_indicator_style(&self) -> IndicatorStyle {
+        if self.classify {
+            IndicatorStyle::Classify
+        } else if self.file_type {
+            IndicatorStyle::FileType
+        } else if self.slash {
+            IndicatorStyle::Slash
+        } else {
+            self.indicator_style.unwrap_or_default()
+        }
+    }
+
+    /// Determines the quoting style from -Q, -b, -N and --quoting-style.
+    /// Defaults to shell-escape on a terminal so names can be copy-pasted,
+    /// and to literal output otherwise. --zero always prints names literally.
+    fn get_quoting_style(&self) -> QuotingStyle {
+        if self.zero {
+            QuotingStyle::Literal
+        } else if self.quote_name {
+            QuotingStyle::C
+        } else if self.escape {
+            QuotingStyle::Escape
+        } else if self.literal {
+            QuotingStyle::Literal
+        } else if let Some(style) = self.quoting_style {
+            style
+        } else if io::stdout().is_terminal() {
+            QuotingStyle::ShellEscape
+        } else {
+            QuotingStyle::Literal
+        }
+    }
+
+    /// Determines the display format.
+    /// -l, -1, -m, -x, -C and --format override each other, so only the last
+    /// one given is set. -n, -g and -o imply -l unless another format is chosen.
+    /// Without any of these, output is in columns on a terminal and one entry
+    /// per line otherwise (always, with --zero).
     fn get_format(&self) -> Format {
-        if self.one {
-            Format::Name
-        } else if self.long {
-            Format::Long
-        } else if self.size {
-            Format::WithSize
+        let format = if self.long {
+            Some(Format::Long)
+        } else if self.one {
+            Some(Format::SingleColumn)
+        } else if self.commas {
+            Some(Format::Commas)
+        } else if self.across {
+            Some(Format::Across)
+        } else if self.vertical {
+            Some(Format::Vertical)
         } else {
-            Format::Name
+            self.format
+        };
+
+        match format {
+            _ if self.zero && format != Some(Format::Long) => Format::SingleColumn,
+            Some(format) => format,
+            None if self.numeric_ids || self.no_owner || self.long_no_group => Format::Long,
+            None if io::stdout().is_terminal() => Format::Vertical,
+            None => Format::SingleColumn,
         }
     }
 }
 
+/// State shared by the listings of one run
+#[derive(Default)]
+struct Run {
+    overall: Summary, // Statistics of every listing, for --summary
+    sizes: TreeSizes, // Directory tree sizes computed so far (--total-size)
+    filter: Filter,   // Tests an entry must pass to be listed
+}
+
+/// Prints one listing's statistics line and adds it to the run's totals
+fn print_summary(
+    summary: &Summary,
+    config: &FormatConfig,
+    output: &mut Output,
+    overall: &mut Summary,
+) -> io::Result<()> {
+    output.line(format_args!("summary: {}", summary.render(config)))?;
+    overall.merge(summary);
+    Ok(())
+}
+
+/// Reads a default size unit from LS_BLOCK_SIZE or BLOCK_SIZE, as GNU ls does
+fn block_size_from_env() -> Option<BlockSize> {
+    ["LS_BLOCK_SIZE", "BLOCK_SIZE"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok()?.parse().ok())
+}
+
 fn main() {
     let args = Args::parse();
+    // clap conflicts can't depend on the value given to --links
+    if args.links == Some(LinksMode::Groups) && args.flat {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "the argument '--links=groups' cannot be used with '--flat'",
+            )
+            .exit();
+    }
 
     if let Err(e) = args.run() {
         eprintln!("Error: {e}");
@@ -0,0 +1,175 @@
+//! Output layer for listings.
+//!
+//! All listing output goes through the Output struct so that line
+//! terminators can be switched to NUL (`--zero`) in one place.
+
+use crate::file_info::FileInfo;
+use crate::formatter::{ColumnWidths, FileInfoFormatter, Format, FormatConfig};
+use crate::layout::{self, Cell};
+use crate::utils::{display_width, IndicatorStyle};
+use std::fmt::Display;
+use std::io::{self, StdoutLock, Write};
+use std::os::unix::ffi::OsStrExt;
+
+/// Writes listing lines to stdout, each ending with the configured terminator
+pub struct Output {
+    out: StdoutLock<'static>, // Locked stdout, line-buffered by the standard library
+    zero: bool,               // End entries with NUL instead of newline
+}
+
+impl Output {
+    /// Creates an output writer; `zero` selects NUL-terminated entries
+    pub fn new(zero: bool) -> Self {
+        Output {
+            out: io::stdout().lock(),
+            zero,
+        }
+    }
+
+    /// Whether entries are NUL-terminated (--zero)
+    pub fn is_zero(&self) -> bool {
+        self.zero
+    }
+
+    /// Writes one line of text followed by the terminator
+    pub fn line(&mut self, text: impl Display) -> io::Result<()> {
+        write!(self.out, "{text}")?;
+        self.terminate()
+    }
+
+    /// Writes a directory header (e.g., "src:")
+    pub fn header(&mut self, name: &str) -> io::Result<()> {
+        writeln!(self.out, "{name}:")
+    }
+
+    /// Writes the blank line that separates directory listings
+    pub fn separator(&mut self) -> io::Result<()> {
+        writeln!(self.out)
+    }
+
+    /// Writes a group of entries in the configured format, laying them out
+    /// in a grid or comma-separated stream where the format calls for it
+    pub fn entries(&mut self, entries: &[FileInfo], config: &FormatConfig) -> io::Result<()> {
+        let widths = if config.format == Format::Long
+            || config.show_context
+            || config.show_caps
+            || config.show_links
+        {
+            ColumnWidths::measure(entries, config)
+        } else {
+            ColumnWidths::default()
+        };
+        // Detail lines under an entry (-@, --acl) need every entry on its own
+        // line, so they switch grid and comma formats to one entry per line
+        let one_per_line = matches!(config.format, Format::Long | Format::SingleColumn)
+            || config.show_xattrs
+            || config.show_acl;
+        if one_per_line {
+            for file_info in entries {
+                self.entry(file_info, config, widths)?;
+            }
+            report_unreadable(entries);
+            return Ok(());
+        }
+        let across = config.format == Format::Across;
+
+        let cells: Vec<Cell> = entries
+            .iter()
+            .map(|file_info| {
+                let text = FileInfoFormatter {
+                    file_info,
+                    config,
+                    // Contexts and capabilities are not padded in a comma-separated stream
+                    widths: if config.format == Format::Commas {
+                        ColumnWidths::default()
+                    } else {
+                        widths
+                    },
+                }
+                .to_string();
+                let width = display_width(&text);
+                Cell { text, width }
+            })
+            .collect();
+        let lines = if config.format == Format::Commas {
+            layout::commas(&cells, config.line_width)
+        } else {
+            layout::grid(&cells, config.line_width, across)
+        };
+
+        for line in lines {
+            self.line(line)?;
+        }
+        report_unreadable(entries);
+        Ok(())
+    }
+
+    /// Writes one entry as soon as it is read (--stream). Columns use their
+    /// minimum widths, and grid formats print one entry per line.
+    pub fn stream_entry(&mut self, file_info: &FileInfo, config: &FormatConfig) -> io::Result<()> {
+        self.entry(file_info, config, ColumnWidths::default())?;
+        report_unreadable(std::slice::from_ref(file_info));
+        Ok(())
+    }
+
+    /// Writes a single entry using the run's display settings.
+    /// Plain names in --zero mode are written as raw bytes, so names that are
+    /// not valid UTF-8 survive a round trip through `xargs -0` unchanged.
+    fn entry(
+        &mut self,
+        file_info: &FileInfo,
+        config: &FormatConfig,
+        widths: ColumnWidths,
+    ) -> io::Result<()> {
+        let plain_name = config.format == Format::SingleColumn
+            && !config.show_inode
+            && !config.show_blocks
+            && !config.show_context
+            && !config.show_caps
+            && !config.show_links
+            && config.indicator_style == IndicatorStyle::None;
+
+        if self.zero && plain_name {
+            let name = if config.full_paths {
+                file_info.path.as_os_str()
+            } else {
+                &file_info.raw_name
+            };
+            self.out.write_all(name.as_bytes())?;
+            self.terminate()
+        } else {
+            let formatter = FileInfoFormatter {
+                file_info,
+                config,
+                widths,
+            };
+            self.line(&formatter)?;
+            if config.show_acl {
+                for line in formatter.acl_lines() {
+                    self.line(line)?;
+                }
+            }
+            if config.show_xattrs {
+                for line in formatter.xattr_lines() {
+                    self.line(line)?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Writes the entry terminator
+    fn terminate(&mut self) -> io::Result<()> {
+        self.out.write_all(if self.zero { b"\0" } else { b"\n" })
+    }
+}
+
+/// Reports entries whose metadata was needed for the listing but couldn't
+/// be read (they are shown with '?' columns)
+pub fn report_unreadable(entries: &[FileInfo]) {
+    for file_info in entries {
+        if let Some(e) = file_info.metadata_error() {
+            eprintln!("ls: cannot access '{}': {e}", file_info.path.display());
+        }
+    }
+}
@@ -0,0 +1,749 @@
+//! Query expressions for `--where`.
+//!
+//! An expression such as `size > 1M and ext in ["log", "txt"] and
+//! mtime < now - 30d` is parsed once into a typed tree and evaluated against
+//! each entry. Every operand has one of five types (number, duration, time,
+//! string, boolean), checked while parsing, so a mistake like `size > "big"`
+//! is reported with the column it was found at before anything is listed.
+//!
+//! Grammar, loosest binding first:
+//!
+//! ```text
+//! or         = and ("or" and)*
+//! and        = not ("and" not)*
+//! not        = "not" not | "(" or ")" | comparison
+//! comparison = operand (("==" | "!=" | "<" | "<=" | ">" | ">=") operand
+//!                      | "in" "[" operand ("," operand)* "]"
+//!                      | "=~" string | "matches" string)?
+//! operand    = term (("+" | "-") term)*
+//! term       = field | "now" | "true" | "false" | number | string
+//! ```
+
+use crate::file_info::FileInfo;
+use crate::filter::type_letter;
+use crate::ids::IdResolver;
+use crate::utils::TimeField;
+use glob::Pattern;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+/// The type of an operand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Number,   // Counts and sizes in bytes
+    Duration, // Lengths of time, in nanoseconds
+    Time,     // Points in time, in nanoseconds since the epoch
+    String,   // Names and paths
+    Bool,     // Flags such as `hidden`
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Type::Number => "a number",
+            Type::Duration => "a duration",
+            Type::Time => "a time",
+            Type::String => "a string",
+            Type::Bool => "a boolean",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Entry fields an expression can refer to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Name,      // File name
+    Path,      // Path as listed
+    Ext,       // Lowercased extension, "" if none
+    Type,      // `find` type letter: f, d, l, p, s, b or c
+    Size,      // Apparent size in bytes
+    Allocated, // Allocated size in bytes
+    Mtime,     // Modification time
+    Atime,     // Access time
+    Ctime,     // Status change time
+    Btime,     // Birth time
+    Uid,       // Owner id
+    Gid,       // Group id
+    User,      // Owner name
+    Group,     // Group name
+    Perm,      // Permission bits, e.g. 0644
+    Nlink,     // Hard link count
+    Inode,     // Inode number
+    Mount,     // Mount id (statx)
+    Hidden,    // Name starts with '.'
+}
+
+/// Field names, as written in expressions
+const FIELDS: [(&str, Field); 19] = [
+    ("name", Field::Name),
+    ("path", Field::Path),
+    ("ext", Field::Ext),
+    ("type", Field::Type),
+    ("size", Field::Size),
+    ("allocated", Field::Allocated),
+    ("mtime", Field::Mtime),
+    ("atime", Field::Atime),
+    ("ctime", Field::Ctime),
+    ("btime", Field::Btime),
+    ("uid", Field::Uid),
+    ("gid", Field::Gid),
+    ("user", Field::User),
+    ("group", Field::Group),
+    ("perm", Field::Perm),
+    ("nlink", Field::Nlink),
+    ("inode", Field::Inode),
+    ("mount", Field::Mount),
+    ("hidden", Field::Hidden),
+];
+
+impl Field {
+    /// The type of the field's values
+    fn ty(self) -> Type {
+        match self {
+            Field::Name | Field::Path | Field::Ext | Field::Type | Field::User | Field::Group => {
+                Type::String
+            }
+            Field::Mtime | Field::Atime | Field::Ctime | Field::Btime => Type::Time,
+            Field::Hidden => Type::Bool,
+            _ => Type::Number,
+        }
+    }
+}
+
+/// A value produced while evaluating. Numbers, durations and times are all
+/// integers; the parser has already checked which is which.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Value {
+    Int(i128),
+    Str(String),
+    Bool(bool),
+}
+
+/// Something that evaluates to a value
+#[derive(Debug, Clone)]
+enum Operand {
+    Field(Field),                          // A property of the entry
+    Literal(Value),                        // A constant
+    Now,                                   // The time the run started
+    Add(Box<Operand>, Box<Operand>, i128), // Sum (sign 1) or difference (sign -1)
+}
+
+/// Comparison operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CmpOp {
+    Eq, // ==
+    Ne, // !=
+    Lt, // <
+    Le, // <=
+    Gt, // >
+    Ge, // >=
+}
+
+/// A boolean expression
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, CmpOp, Operand),
+    In(Operand, Vec<Operand>),
+    Regex(Operand, Regex),
+    Glob(Operand, Pattern),
+    Flag(Operand), // A boolean operand on its own, e.g. `hidden`
+}
+
+/// A parsed `--where` expression
+#[derive(Debug, Clone)]
+pub struct Query {
+    expr: Expr, // The expression tree
+}
+
+impl FromStr for Query {
+    type Err = String;
+
+    /// Parses an expression. Errors name the problem and point at the
+    /// offending column on a copy of the expression.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = || {
+            let mut parser = Parser {
+                tokens: tokenize(s)?,
+                position: 0,
+            };
+            let expr = parser.parse_or()?;
+            match parser.peek() {
+                (Token::End, _) => Ok(expr),
+                (token, column) => Err(SyntaxError::new(
+                    format!("unexpected {token} after the end of the expression"),
+                    column,
+                )),
+            }
+        };
+        parse().map(|expr| Query { expr }).map_err(|e| {
+            format!(
+                "{} at column {}\n  {s}\n  {}^",
+                e.message,
+                e.column,
+                " ".repeat(e.column - 1)
+            )
+        })
+    }
+}
+
+/// What an expression is evaluated with
+pub struct Context<'a> {
+    pub now: SystemTime,     // Value of `now`
+    pub ids: &'a IdResolver, // Name lookups for `user` and `group`
+}
+
+impl Query {
+    /// Whether an entry satisfies the expression. Comparisons involving a
+    /// field that can't be read (missing metadata or birth time) are false.
+    pub fn matches(&self, file_info: &FileInfo, context: &Context) -> bool {
+        self.expr.eval(file_info, context)
+    }
+}
+
+impl Expr {
+    fn eval(&self, file_info: &FileInfo, context: &Context) -> bool {
+        let value = |operand: &Operand| operand.eval(file_info, context);
+        match self {
+            Expr::And(a, b) => a.eval(file_info, context) && b.eval(file_info, context),
+            Expr::Or(a, b) => a.eval(file_info, context) || b.eval(file_info, context),
+            Expr::Not(a) => !a.eval(file_info, context),
+            Expr::Compare(a, op, b) => match (value(a), value(b)) {
+                (Some(a), Some(b)) => {
+                    let ordering = a.cmp(&b);
+                    match op {
+                        CmpOp::Eq => ordering == Ordering::Equal,
+                        CmpOp::Ne => ordering != Ordering::Equal,
+                        CmpOp::Lt => ordering == Ordering::Less,
+                        CmpOp::Le => ordering != Ordering::Greater,
+                        CmpOp::Gt => ordering == Ordering::Greater,
+                        CmpOp::Ge => ordering != Ordering::Less,
+                    }
+                }
+                _ => false,
+            },
+            Expr::In(a, list) => {
+                value(a).is_some_and(|a| list.iter().any(|item| value(item).as_ref() == Some(&a)))
+            }
+            Expr::Regex(a, regex) => {
+                matches!(value(a), Some(Value::Str(text)) if regex.is_match(&text))
+            }
+            Expr::Glob(a, pattern) => {
+                matches!(value(a), Some(Value::Str(text)) if pattern.matches(&text))
+            }
+            Expr::Flag(a) => value(a) == Some(Value::Bool(true)),
+        }
+    }
+}
+
+impl Operand {
+    fn eval(&self, file_info: &FileInfo, context: &Context) -> Option<Value> {
+        match self {
+            Operand::Field(field) => field_value(*field, file_info, context),
+            Operand::Literal(value) => Some(value.clone()),
+            Operand::Now => Some(Value::Int(nanos(context.now))),
+            Operand::Add(a, b, sign) => {
+                match (a.eval(file_info, context)?, b.eval(file_info, context)?) {
+                    (Value::Int(a), Value::Int(b)) => Some(Value::Int(a + sign * b)),
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+/// Reads a field of an entry
+fn field_value(field: Field, file_info: &FileInfo, context: &Context) -> Option<Value> {
+    let int = |n: u64| Some(Value::Int(i128::from(n)));
+    let text = |s: String| Some(Value::Str(s));
+    let time = |field| file_info.time(field).map(|time| Value::Int(nanos(time)));
+    match field {
+        Field::Name => text(file_info.file_name.clone()),
+        Field::Path => text(file_info.path.to_string_lossy().to_string()),
+        Field::Ext => text(
+            file_info
+                .path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .unwrap_or_default(),
+        ),
+        Field::Type => text(type_letter(file_info.file_type()).to_string()),
+        Field::Hidden => Some(Value::Bool(file_info.is_hidden())),
+        Field::Inode => int(file_info.inode()),
+        Field::Mount => int(file_info.mount_id()?),
+        Field::Mtime => time(TimeField::Mtime),
+        Field::Atime => time(TimeField::Atime),
+        Field::Ctime => time(TimeField::Ctime),
+        Field::Btime => time(TimeField::Birth),
+        // The rest come from metadata, which may be unreadable
+        _ => {
+            file_info.metadata()?;
+            match field {
+                Field::Size => int(file_info.size()),
+                Field::Allocated => int(file_info.blocks() * 512),
+                Field::Uid => int(u64::from(file_info.uid())),
+                Field::Gid => int(u64::from(file_info.gid())),
+                Field::User => text(context.ids.user_name(file_info.uid())),
+                Field::Group => text(context.ids.group_name(file_info.gid())),
+                Field::Perm => int(u64::from(file_info.mode() & 0o7777)),
+                Field::Nlink => int(file_info.nlink()),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Nanoseconds since the epoch, negative before 1970
+fn nanos(time: SystemTime) -> i128 {
+    match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(after) => i128::try_from(after.as_nanos()).unwrap_or(i128::MAX),
+        Err(before) => -i128::try_from(before.duration().as_nanos()).unwrap_or(i128::MAX),
+    }
+}
+
+/// A parse error and the 1-based column it applies to
+struct SyntaxError {
+    message: String, // What went wrong
+    column: usize,   // Where, counted in characters
+}
+
+impl SyntaxError {
+    fn new(message: String, column: usize) -> Self {
+        SyntaxError { message, column }
+    }
+}
+
+/// A lexical token
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),         // Field names and keywords
+    Number(String),       // Digits with an optional fraction and unit suffix
+    Str(String),          // Quoted string, with escapes resolved
+    Symbol(&'static str), // Operators and punctuation
+    End,                  // End of the expression
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "'{word}'"),
+            Token::Number(number) => write!(f, "number '{number}'"),
+            Token::Str(text) => write!(f, "string \"{text}\""),
+            Token::Symbol(symbol) => write!(f, "'{symbol}'"),
+            Token::End => f.write_str("end of expression"),
+        }
+    }
+}
+
+/// Operators and punctuation, longest first so "<=" wins over "<"
+const SYMBOLS: [&str; 18] = [
+    "==", "!=", "<=", ">=", "=~", "&&", "||", "<", ">", "=", "!", "(", ")", "[", "]", ",", "+", "-",
+];
+
+/// Splits an expression into tokens, each with its 1-based column
+fn tokenize(s: &str) -> Result<Vec<(Token, usize)>, SyntaxError> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((Token::Word(chars[start..i].iter().collect()), column));
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push((Token::Number(chars[start..i].iter().collect()), column));
+        } else if c == '"' || c == '\'' {
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => {
+                        return Err(SyntaxError::new("unterminated string".to_string(), column));
+                    }
+                    Some(&end) if end == c => break,
+                    Some('\\') if chars.get(i + 1).is_some() => {
+                        // Only quotes and backslashes are escaped; other
+                        // backslashes are kept for regular expressions
+                        let next = chars[i + 1];
+                        if next != c && next != '\\' {
+                            text.push('\\');
+                        }
+                        text.push(next);
+                        i += 1;
+                    }
+                    Some(&other) => text.push(other),
+                }
+                i += 1;
+            }
+            i += 1;
+            tokens.push((Token::Str(text), column));
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(**symbol))
+                .ok_or_else(|| SyntaxError::new(format!("unexpected character '{c}'"), column))?;
+            i += symbol.len();
+            tokens.push((Token::Symbol(symbol), column));
+        }
+    }
+
+    tokens.push((Token::End, chars.len() + 1));
+    Ok(tokens)
+}
+
+/// Recursive descent parser over the token list
+struct Parser {
+    tokens: Vec<(Token, usize)>, // Tokens with their columns, ending with End
+    position: usize,             // Index of the next token
+}
+
+impl Parser {
+    /// Returns the next token without consuming it
+    fn peek(&self) -> (Token, usize) {
+        self.tokens[self.position].clone()
+    }
+
+    /// Consumes and returns the next token
+    fn next(&mut self) -> (Token, usize) {
+        let token = self.peek();
+        if token.0 != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    /// Consumes the next token if it is one of the given words or symbols
+    fn accept(&mut self, words: &[&str]) -> bool {
+        let found = match &self.peek().0 {
+            Token::Word(word) => words.contains(&word.as_str()),
+            Token::Symbol(symbol) => words.contains(symbol),
+            _ => false,
+        };
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    /// Consumes a required symbol
+    fn expect(&mut self, symbol: &'static str) -> Result<(), SyntaxError> {
+        match self.next() {
+            (Token::Symbol(found), _) if found == symbol => Ok(()),
+            (token, column) => Err(SyntaxError::new(
+                format!("expected '{symbol}' but found {token}"),
+                column,
+            )),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, SyntaxError> {
+        let mut expr = self.parse_and()?;
+        while self.accept(&["or", "||"]) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, SyntaxError> {
+        let mut expr = self.parse_not()?;
+        while self.accept(&["and", "&&"]) {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, SyntaxError> {
+        if self.accept(&["not", "!"]) {
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        if self.accept(&["("]) {
+            let expr = self.parse_or()?;
+            self.expect(")")?;
+            return Ok(expr);
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, SyntaxError> {
+        let (left, left_type, left_column) = self.parse_operand()?;
+        let (token, column) = self.peek();
+
+        let op = match &token {
+            Token::Symbol("==" | "=") => Some(CmpOp::Eq),
+            Token::Symbol("!=") => Some(CmpOp::Ne),
+            Token::Symbol("<") => Some(CmpOp::Lt),
+            Token::Symbol("<=") => Some(CmpOp::Le),
+            Token::Symbol(">") => Some(CmpOp::Gt),
+            Token::Symbol(">=") => Some(CmpOp::Ge),
+            _ => None,
+        };
+        if let Some(op) = op {
+            self.next();
+            let (right, right_type, right_column) = self.parse_operand()?;
+            check_same(left_type, right_type, right_column)?;
+            if left_type == Type::Bool && !matches!(op, CmpOp::Eq | CmpOp::Ne) {
+                return Err(SyntaxError::new(
+                    format!("booleans can't be compared with {token}"),
+                    column,
+                ));
+            }
+            return Ok(Expr::Compare(left, op, right));
+        }
+
+        match &token {
+            Token::Word(word) if word == "in" => {
+                self.next();
+                self.expect("[")?;
+                let mut items = Vec::new();
+                loop {
+                    let (item, item_type, item_column) = self.parse_operand()?;
+                    check_same(left_type, item_type, item_column)?;
+                    items.push(item);
+                    if !self.accept(&[","]) {
+                        break;
+                    }
+                }
+                self.expect("]")?;
+                Ok(Expr::In(left, items))
+            }
+            _ if is_match_operator(&token) => {
+                self.next();
+                check_same(Type::String, left_type, left_column)?;
+                let (pattern, pattern_column) = match self.next() {
+                    (Token::Str(pattern), column) => (pattern, column),
+                    (token, column) => {
+                        return Err(SyntaxError::new(
+                            format!("expected a quoted pattern but found {token}"),
+                            column,
+                        ));
+                    }
+                };
+                if token == Token::Symbol("=~") {
+                    Regex::new(&pattern)
+                        .map(|regex| Expr::Regex(left, regex))
+                        .map_err(|e| {
+                            SyntaxError::new(
+                                format!("invalid regular expression: {e}"),
+                                pattern_column,
+                            )
+                        })
+                } else {
+                    Pattern::new(&pattern)
+                        .map(|glob| Expr::Glob(left, glob))
+                        .map_err(|e| {
+                            SyntaxError::new(
+                                format!("invalid glob pattern: {}", e.msg),
+                                pattern_column,
+                            )
+                        })
+                }
+            }
+            _ if left_type == Type::Bool => Ok(Expr::Flag(left)),
+            _ => Err(SyntaxError::new(
+                format!("expected a comparison after {left_type} but found {token}"),
+                column,
+            )),
+        }
+    }
+
+    /// Parses a sum or difference of terms, returning its type and column
+    fn parse_operand(&mut self) -> Result<(Operand, Type, usize), SyntaxError> {
+        let (mut operand, mut ty, column) = self.parse_term()?;
+        loop {
+            let (token, op_column) = self.peek();
+            let sign = match token {
+                Token::Symbol("+") => 1,
+                Token::Symbol("-") => -1,
+                _ => break,
+            };
+            self.next();
+            let (right, right_type, _) = self.parse_term()?;
+            ty = match (ty, right_type, sign) {
+                (Type::Number, Type::Number, _) => Type::Number,
+                (Type::Duration, Type::Duration, _) => Type::Duration,
+                (Type::Time, Type::Duration, _) => Type::Time,
+                (Type::Duration, Type::Time, 1) => Type::Time,
+                (Type::Time, Type::Time, -1) => Type::Duration,
+                _ => {
+                    let verb = if sign == 1 { "add" } else { "subtract" };
+                    return Err(SyntaxError::new(
+                        format!("can't {verb} {right_type} and {ty}"),
+                        op_column,
+                    ));
+                }
+            };
+            operand = Operand::Add(Box::new(operand), Box::new(right), sign);
+        }
+        Ok((operand, ty, column))
+    }
+
+    /// Parses a field, keyword or literal
+    fn parse_term(&mut self) -> Result<(Operand, Type, usize), SyntaxError> {
+        let (token, column) = self.next();
+        let term = match token {
+            Token::Word(word) => match word.as_str() {
+                "now" => (Operand::Now, Type::Time),
+                "true" => (Operand::Literal(Value::Bool(true)), Type::Bool),
+                "false" => (Operand::Literal(Value::Bool(false)), Type::Bool),
+                _ => {
+                    let field = FIELDS
+                        .iter()
+                        .find(|(name, _)| *name == word)
+                        .map(|(_, field)| *field)
+                        .ok_or_else(|| {
+                            let names: Vec<_> = FIELDS.iter().map(|(name, _)| *name).collect();
+                            SyntaxError::new(
+                                format!("unknown field '{word}' (fields: {})", names.join(", ")),
+                                column,
+                            )
+                        })?;
+                    (Operand::Field(field), field.ty())
+                }
+            },
+            Token::Number(number) => {
+                let (value, ty) = parse_number(&number).ok_or_else(|| {
+                    SyntaxError::new(format!("invalid number '{number}'"), column)
+                })?;
+                (Operand::Literal(Value::Int(value)), ty)
+            }
+            Token::Str(text) => (Operand::Literal(Value::Str(text)), Type::String),
+            token => {
+                return Err(SyntaxError::new(
+                    format!("expected a field or value but found {token}"),
+                    column,
+                ));
+            }
+        };
+        Ok((term.0, term.1, column))
+    }
+}
+
+/// Whether a token is the regex (`=~`) or glob (`matches`) operator
+fn is_match_operator(token: &Token) -> bool {
+    matches!(token, Token::Symbol("=~")) || matches!(token, Token::Word(word) if word == "matches")
+}
+
+/// Checks that an operand has the type the other side of its operator has
+fn check_same(expected: Type, found: Type, column: usize) -> Result<(), SyntaxError> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(SyntaxError::new(
+            format!("expected {expected} but found {found}"),
+            column,
+        ))
+    }
+}
+
+/// Parses a number literal. A size suffix (`k`/`K`, `M`, `G`, `T`, `P`,
+/// powers of 1024) makes a byte count, a time suffix (`s`, `m`, `h`, `d`,
+/// `w`, `y`) a duration; a fraction needs a suffix. A leading 0 means octal,
+/// for permissions.
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+fn parse_number(number: &str) -> Option<(i128, Type)> {
+    let digits_end = number
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(number.len());
+    let (digits, suffix) = number.split_at(digits_end);
+
+    const SECOND: i128 = 1_000_000_000;
+    let (multiplier, ty) = match suffix {
+        "" => {
+            if digits.len() > 1 && digits.starts_with('0') {
+                return i128::from_str_radix(digits, 8)
+                    .ok()
+                    .map(|n| (n, Type::Number));
+            }
+            return digits.parse().ok().map(|n| (n, Type::Number));
+        }
+        "k" | "K" => (1 << 10, Type::Number),
+        "M" => (1 << 20, Type::Number),
+        "G" => (1 << 30, Type::Number),
+        "T" => (1 << 40, Type::Number),
+        "P" => (1 << 50, Type::Number),
+        "s" => (SECOND, Type::Duration),
+        "m" => (60 * SECOND, Type::Duration),
+        "h" => (60 * 60 * SECOND, Type::Duration),
+        "d" => (24 * 60 * 60 * SECOND, Type::Duration),
+        "w" => (7 * 24 * 60 * 60 * SECOND, Type::Duration),
+        "y" => (365 * 24 * 60 * 60 * SECOND, Type::Duration),
+        _ => return None,
+    };
+
+    let value = if digits.contains('.') {
+        let value: f64 = digits.parse().ok()?;
+        (value * multiplier as f64).round() as i128
+    } else {
+        digits.parse::<i128>().ok()?.checked_mul(multiplier)?
+    };
+    Some((value, ty))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(expr: &str) -> String {
+        expr.parse::<Query>().unwrap_err()
+    }
+
+    #[test]
+    fn test_parse_numbers() {
+        assert_eq!(parse_number("1M"), Some((1 << 20, Type::Number)));
+        assert_eq!(parse_number("1.5k"), Some((1536, Type::Number)));
+        assert_eq!(parse_number("0644"), Some((0o644, Type::Number)));
+        assert_eq!(
+            parse_number("2h"),
+            Some((7_200_000_000_000, Type::Duration))
+        );
+        assert_eq!(parse_number("1.5"), None);
+        assert_eq!(parse_number("3X"), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(
+            "size > 1M and ext in [\"log\", \"txt\"] and mtime < now - 30d"
+                .parse::<Query>()
+                .is_ok()
+        );
+        assert!("not hidden and (name matches \"*.rs\" or path =~ 'src/.*')"
+            .parse::<Query>()
+            .is_ok());
+        assert_eq!(
+            error("size > \"big\""),
+            "expected a number but found a string at column 8\n  size > \"big\"\n         ^"
+        );
+        assert!(error("sise > 1").starts_with("unknown field 'sise'"));
+        assert!(error("mtime < now + 1M").starts_with("can't add a number and a time at column 13"));
+        assert!(error("size > 1Q").starts_with("invalid number '1Q' at column 8"));
+        assert!(error("name =~ \"(\"").contains("at column 9"));
+        assert!(error("size > 1 size").starts_with("unexpected 'size' after the end"));
+        assert!(
+            error("(size > 1").starts_with("expected ')' but found end of expression at column 10")
+        );
+        assert!(error("size").starts_with("expected a comparison after a number"));
+    }
+}
@@ -0,0 +1,280 @@
+//! File name quoting and escaping.
+//!
+//! This module renders raw file names (which may contain spaces, control
+//! characters or invalid UTF-8) using the GNU ls quoting styles, so names are
+//! safe to print to a terminal and, in the shell styles, safe to copy-paste.
+
+use crate::utils::{display_width, truncate_to_width};
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+
+/// Quoting styles accepted by `--quoting-style`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum QuotingStyle {
+    #[default]
+    Literal,     // Print names as-is
+    Shell,       // Quote for the shell only when needed
+    ShellAlways, // Always quote for the shell
+    ShellEscape, // Like shell, but write control characters as $'\n' escapes
+    C,           // C string in double quotes with backslash escapes
+    Escape,      // Like c, but without the surrounding quotes
+    Locale,      // Like escape, but in ‘locale’ quotation marks
+}
+
+/// A decoded piece of a file name
+enum Piece {
+    Char(char), // A printable character
+    Byte(u8),   // A control character or invalid UTF-8 byte
+}
+
+/// Characters that never need quoting in the shell styles
+fn is_shell_safe(c: char) -> bool {
+    c.is_alphanumeric() || "%+,-./:=@_".contains(c)
+}
+
+/// Splits a raw name into printable characters and non-printable bytes
+fn pieces(name: &OsStr) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    for chunk in name.as_bytes().utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if c.is_control() {
+                let mut buf = [0; 4];
+                pieces.extend(c.encode_utf8(&mut buf).bytes().map(Piece::Byte));
+            } else {
+                pieces.push(Piece::Char(c));
+            }
+        }
+        pieces.extend(chunk.invalid().iter().copied().map(Piece::Byte));
+    }
+    pieces
+}
+
+/// Writes a non-printable byte as a C escape sequence
+fn c_escape(byte: u8, out: &mut String) {
+    match byte {
+        0x07 => out.push_str("\\a"),
+        0x08 => out.push_str("\\b"),
+        b'\t' => out.push_str("\\t"),
+        b'\n' => out.push_str("\\n"),
+        0x0b => out.push_str("\\v"),
+        0x0c => out.push_str("\\f"),
+        b'\r' => out.push_str("\\r"),
+        _ => out.push_str(&format!("\\{byte:03o}")),
+    }
+}
+
+/// Renders pieces without any escaping. Non-printable bytes become `?` when
+/// `hide_control` is set; otherwise ASCII control characters pass through and
+/// invalid UTF-8 is replaced, since the result must be a `String`.
+fn literal(pieces: &[Piece], hide_control: bool) -> String {
+    pieces
+        .iter()
+        .map(|piece| match piece {
+            Piece::Char(c) => *c,
+            Piece::Byte(_) if hide_control => '?',
+            Piece::Byte(b) if b.is_ascii() => char::from(*b),
+            Piece::Byte(_) => char::REPLACEMENT_CHARACTER,
+        })
+        .collect()
+}
+
+/// Wraps text in shell quotes, preferring double quotes for names that
+/// contain a single quote and nothing special to double quotes
+fn shell_quote(text: &str) -> String {
+    if text.contains('\'') && !text.contains(['"', '$', '`', '\\', '!']) {
+        format!("\"{text}\"")
+    } else {
+        format!("'{}'", text.replace('\'', "'\\''"))
+    }
+}
+
+/// Renders pieces in C style; `quote` is the character escaped with a backslash
+fn c_style(pieces: &[Piece], quote: Option<char>, escape_space: bool) -> String {
+    let mut out = String::new();
+    for piece in pieces {
+        match piece {
+            Piece::Char('\\') => out.push_str("\\\\"),
+            Piece::Char(' ') if escape_space => out.push_str("\\ "),
+            Piece::Char(c) if Some(*c) == quote => {
+                out.push('\\');
+                out.push(*c);
+            }
+            Piece::Char(c) => out.push(*c),
+            Piece::Byte(b) => c_escape(*b, &mut out),
+        }
+    }
+    out
+}
+
+/// Renders a name in shell-escape style: printable runs are shell quoted and
+/// runs of control bytes are written as `$'...'` ANSI-C strings
+fn shell_escape(pieces: &[Piece]) -> String {
+    let mut out = String::new();
+    let mut run = String::new();
+    let mut bytes = String::new();
+
+    for piece in pieces {
+        match piece {
+            Piece::Char(c) => {
+                if !bytes.is_empty() {
+                    out.push_str(&format!("$'{bytes}'"));
+                    bytes.clear();
+                }
+                run.push(*c);
+            }
+            Piece::Byte(b) => {
+                if !run.is_empty() {
+                    out.push_str(&shell_quote(&run));
+                    run.clear();
+                }
+                c_escape(*b, &mut bytes);
+            }
+        }
+    }
+    if !bytes.is_empty() {
+        out.push_str(&format!("$'{bytes}'"));
+    }
+    if !run.is_empty() {
+        out.push_str(&shell_quote(&run));
+    }
+    out
+}
+
+/// Quotes a raw file name in the given style.
+/// `hide_control` (-q) replaces non-printable characters with `?` in the
+/// styles that would otherwise print them unescaped.
+pub fn quote_name(name: &OsStr, style: QuotingStyle, hide_control: bool) -> String {
+    let pieces = pieces(name);
+    let needs_quotes = pieces.is_empty()
+        || pieces.iter().any(|piece| match piece {
+            Piece::Char(c) => !is_shell_safe(*c),
+            Piece::Byte(_) => true,
+        });
+
+    match style {
+        QuotingStyle::Literal => literal(&pieces, hide_control),
+        QuotingStyle::Shell | QuotingStyle::ShellAlways => {
+            let text = literal(&pieces, hide_control);
+            if needs_quotes || style == QuotingStyle::ShellAlways {
+                shell_quote(&text)
+            } else {
+                text
+            }
+        }
+        QuotingStyle::ShellEscape => {
+            if pieces.iter().any(|piece| matches!(piece, Piece::Byte(_))) {
+                shell_escape(&pieces)
+            } else if needs_quotes {
+                shell_quote(&literal(&pieces, false))
+            } else {
+                literal(&pieces, false)
+            }
+        }
+        QuotingStyle::C => format!("\"{}\"", c_style(&pieces, Some('"'), false)),
+        QuotingStyle::Escape => c_style(&pieces, None, true),
+        QuotingStyle::Locale => format!("\u{2018}{}\u{2019}", c_style(&pieces, None, false)),
+    }
+}
+
+/// Quotes a raw file name like `quote_name`, shortened with an ellipsis to
+/// at most `max_width` columns. The raw name is cut before quoting, so
+/// quotes and escapes stay balanced and the ellipsis follows them.
+pub fn quote_name_within(
+    name: &OsStr,
+    style: QuotingStyle,
+    hide_control: bool,
+    max_width: usize,
+) -> String {
+    let quoted = quote_name(name, style, hide_control);
+    if display_width(&quoted) <= max_width {
+        return quoted;
+    }
+
+    let bytes = name.as_bytes();
+    let mut end = bytes.len();
+    while end > 0 {
+        // Step back one character, keeping UTF-8 sequences whole
+        end -= 1;
+        while end > 0 && bytes[end] & 0xC0 == 0x80 {
+            end -= 1;
+        }
+        let shortened = quote_name(OsStr::from_bytes(&bytes[..end]), style, hide_control) + "…";
+        if end > 0 && display_width(&shortened) <= max_width {
+            return shortened;
+        }
+    }
+    truncate_to_width("…", max_width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    fn quote(name: &[u8], style: QuotingStyle) -> String {
+        quote_name(OsStr::from_bytes(name), style, false)
+    }
+
+    #[test]
+    fn test_plain_names_are_unchanged() {
+        for style in [
+            QuotingStyle::Literal,
+            QuotingStyle::Shell,
+            QuotingStyle::ShellEscape,
+            QuotingStyle::Escape,
+        ] {
+            assert_eq!(quote(b"main.rs", style), "main.rs");
+        }
+        assert_eq!(quote(b"main.rs", QuotingStyle::ShellAlways), "'main.rs'");
+        assert_eq!(quote(b"main.rs", QuotingStyle::C), "\"main.rs\"");
+    }
+
+    #[test]
+    fn test_shell_quoting() {
+        assert_eq!(quote(b"my file", QuotingStyle::Shell), "'my file'");
+        assert_eq!(quote(b"it's", QuotingStyle::Shell), "\"it's\"");
+        assert_eq!(
+            quote(b"it's $HOME", QuotingStyle::Shell),
+            "'it'\\''s $HOME'"
+        );
+        assert_eq!(quote(b"", QuotingStyle::Shell), "''");
+    }
+
+    #[test]
+    fn test_shell_escape_control_characters() {
+        assert_eq!(quote(b"a\nb", QuotingStyle::ShellEscape), "'a'$'\\n''b'");
+        assert_eq!(quote(b"\xffx", QuotingStyle::ShellEscape), "$'\\377''x'");
+        assert_eq!(quote(b"a b", QuotingStyle::ShellEscape), "'a b'");
+    }
+
+    #[test]
+    fn test_c_and_escape_styles() {
+        assert_eq!(quote(b"a\"b\tc", QuotingStyle::C), "\"a\\\"b\\tc\"");
+        assert_eq!(quote(b"a b\x1b", QuotingStyle::Escape), "a\\ b\\033");
+        assert_eq!(
+            quote(b"x\\y", QuotingStyle::Locale),
+            "\u{2018}x\\\\y\u{2019}"
+        );
+    }
+
+    #[test]
+    fn test_hide_control_characters() {
+        let name = OsStr::from_bytes(b"a\x1b[31mb");
+        assert_eq!(quote_name(name, QuotingStyle::Literal, true), "a?[31mb");
+        assert_eq!(quote_name(name, QuotingStyle::Literal, false), "a\x1b[31mb");
+    }
+
+    #[test]
+    fn test_quote_name_within() {
+        let quote =
+            |name: &str, style, width| quote_name_within(OsStr::new(name), style, false, width);
+        assert_eq!(quote("a-long-name", QuotingStyle::Literal, 6), "a-lon…");
+        assert_eq!(quote("short", QuotingStyle::Shell, 8), "short");
+        // The closing quote survives truncation
+        assert_eq!(quote("a long name", QuotingStyle::Shell, 7), "'a lo'…");
+        assert_eq!(quote("it's long", QuotingStyle::Shell, 7), "\"it's\"…");
+        assert_eq!(quote("a long name", QuotingStyle::C, 7), "\"a lo\"…");
+        assert_eq!(quote("a long name", QuotingStyle::Shell, 1), "…");
+        assert_eq!(quote("a long name", QuotingStyle::Shell, 0), "");
+    }
+}
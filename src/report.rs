@@ -0,0 +1,368 @@
+//! Capacity report for `--report`.
+//!
+//! A report walks a path and collects a histogram of regular file sizes in
+//! power-of-two buckets, counts and bytes per file extension, and the largest
+//! files and directories. Directory sizes are whole-tree totals, as with
+//! `--total-size`. Reports render as aligned text or as one JSON object.
+
+use crate::directory::collect_entries;
+use crate::file_info::FileInfo;
+use crate::filter::Filter;
+use crate::summary::plural;
+use crate::utils::{format_size, pad_left, pad_right, BlockSize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// Output format selected by `--report` and `--duplicates`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ReportFormat {
+    #[default]
+    Text, // Aligned sections for reading
+    Json, // One JSON object per path
+}
+
+/// File count and byte total for a bucket or extension
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tally {
+    pub files: u64, // Number of regular files
+    pub bytes: u64, // Sum of their apparent sizes
+}
+
+impl Tally {
+    /// Counts one file of the given size
+    fn add(&mut self, size: u64) {
+        self.files += 1;
+        self.bytes += size;
+    }
+}
+
+/// Statistics for one walked path
+#[derive(Debug, Default)]
+pub struct Report {
+    pub path: PathBuf,                      // Path the report was made for
+    pub files: Tally,                       // All regular files
+    pub allocated: u64,                     // Bytes allocated to those files
+    pub dirs: u64,                          // Directories walked below the path
+    pub extensions: HashMap<String, Tally>, // Lowercased extension ("" for none)
+    pub largest_files: Vec<(PathBuf, u64)>, // Largest regular files, biggest first
+    pub largest_dirs: Vec<(PathBuf, u64)>,  // Largest directories by tree size, biggest first
+    top: usize,                             // How many largest entries to keep
+    seen: HashSet<(u64, u64)>,              // Hard-linked files already counted
+    /// Files by size: bucket 0 holds empty files, bucket k sizes in [2^(k-1), 2^k)
+    pub histogram: Vec<Tally>,
+}
+
+impl Report {
+    /// Walks `path` and builds its report. Without `recursive`, only the
+    /// path's own entries are counted, but subdirectories are still sized
+    /// as whole trees. Hidden entries are skipped unless `show_all`.
+    pub fn build(
+        path: &Path,
+        show_all: bool,
+        recursive: bool,
+        top: usize,
+    ) -> std::io::Result<Self> {
+        let mut report = Report {
+            path: path.to_path_buf(),
+            top,
+            ..Report::default()
+        };
+
+        if path.is_dir() {
+            report.walk(path, show_all, recursive, 0);
+        } else {
+            let file_info = FileInfo::from_path(path)?;
+            report.add_file(&file_info);
+        }
+
+        report
+            .largest_files
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        report.largest_files.truncate(top);
+        report
+            .largest_dirs
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        report.largest_dirs.truncate(top);
+        Ok(report)
+    }
+
+    /// Walks one directory and returns the apparent size of everything
+    /// below it. Entries deeper than the counted levels (or hidden ones) are
+    /// only added to the tree size, not to the statistics.
+    fn walk(&mut self, dir: &Path, show_all: bool, recursive: bool, depth: usize) -> u64 {
+        let counted = |file_info: &FileInfo| {
+            (recursive || depth == 0) && (show_all || !file_info.is_hidden())
+        };
+        let entries = match collect_entries(dir, true, &Filter::default(), dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("ls: {}: {e}", dir.display());
+                return 0;
+            }
+        };
+
+        let mut total = 0;
+        for entry in &entries {
+            let count = counted(entry);
+            if entry.is_dir() {
+                let subtree =
+                    entry.size() + self.walk(&entry.path, show_all, recursive && count, depth + 1);
+                if count {
+                    self.dirs += 1;
+                    self.keep_largest_dir(&entry.path, subtree);
+                }
+                total += subtree;
+            } else if self.first_link(entry) {
+                if count {
+                    self.add_file(entry);
+                }
+                total += entry.size();
+            }
+        }
+        total
+    }
+
+    /// Whether this is the first time a file is seen; files with several
+    /// hard links are only counted once
+    fn first_link(&mut self, file_info: &FileInfo) -> bool {
+        match file_info.metadata() {
+            Some(metadata) if metadata.nlink() > 1 => {
+                self.seen.insert((metadata.dev(), metadata.ino()))
+            }
+            _ => true,
+        }
+    }
+
+    /// Adds a regular file to the histogram, extensions and largest files
+    fn add_file(&mut self, file_info: &FileInfo) {
+        if !file_info.file_type().is_file() {
+            return;
+        }
+        let size = file_info.size();
+        self.files.add(size);
+        self.allocated += file_info.blocks() * 512;
+
+        let bucket = bucket(size);
+        if self.histogram.len() <= bucket {
+            self.histogram.resize(bucket + 1, Tally::default());
+        }
+        self.histogram[bucket].add(size);
+
+        let extension = file_info
+            .path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        self.extensions.entry(extension).or_default().add(size);
+
+        self.largest_files.push((file_info.path.clone(), size));
+        self.trim(false);
+    }
+
+    /// Records a directory's tree size for the largest directories
+    fn keep_largest_dir(&mut self, path: &Path, size: u64) {
+        self.largest_dirs.push((path.to_path_buf(), size));
+        self.trim(true);
+    }
+
+    /// Keeps a largest list from growing past twice the wanted length
+    fn trim(&mut self, dirs: bool) {
+        let list = if dirs {
+            &mut self.largest_dirs
+        } else {
+            &mut self.largest_files
+        };
+        if list.len() > 2 * self.top.max(1) {
+            list.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            list.truncate(self.top);
+        }
+    }
+
+    /// Extensions by total bytes, biggest first
+    fn sorted_extensions(&self) -> Vec<(&str, Tally)> {
+        let mut extensions: Vec<_> = self
+            .extensions
+            .iter()
+            .map(|(ext, tally)| (ext.as_str(), *tally))
+            .collect();
+        extensions.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then_with(|| a.0.cmp(b.0)));
+        extensions
+    }
+
+    /// Renders the report as text lines, with sizes in `unit`
+    pub fn to_text(&self, unit: &BlockSize) -> Vec<String> {
+        let size = |bytes| format_size(bytes, unit).trim_start().to_string();
+        let mut lines = vec![format!(
+            "{}, {} apparent, {} allocated; {}",
+            plural(self.files.files, "file", "files"),
+            size(self.files.bytes),
+            size(self.allocated),
+            plural(self.dirs, "directory", "directories")
+        )];
+
+        lines.push("size histogram:".to_string());
+        let rows: Vec<_> = self
+            .histogram
+            .iter()
+            .enumerate()
+            .filter(|(_, tally)| tally.files > 0)
+            .map(|(index, tally)| (bucket_label(index), *tally))
+            .collect();
+        let most = rows.iter().map(|(_, tally)| tally.files).max().unwrap_or(0);
+        lines.extend(tally_rows(&rows, unit, |label, tally| {
+            let bar = usize::try_from(tally.files * 30 / most.max(1)).unwrap_or(0);
+            format!("{label}  {}", "#".repeat(bar.max(1)))
+        }));
+
+        lines.push("extensions:".to_string());
+        let rows: Vec<_> = self
+            .sorted_extensions()
+            .into_iter()
+            .map(|(ext, tally)| {
+                (
+                    if ext.is_empty() { "(none)" } else { ext }.to_string(),
+                    tally,
+                )
+            })
+            .collect();
+        lines.extend(tally_rows(&rows, unit, |label, _| {
+            label.trim_end().to_string()
+        }));
+
+        for (title, list) in [
+            ("largest files:", &self.largest_files),
+            ("largest directories:", &self.largest_dirs),
+        ] {
+            lines.push(title.to_string());
+            let sizes: Vec<_> = list.iter().map(|(_, bytes)| size(*bytes)).collect();
+            let width = sizes.iter().map(String::len).max().unwrap_or(0);
+            for ((path, _), size) in list.iter().zip(&sizes) {
+                lines.push(format!("  {}  {}", pad_left(size, width), path.display()));
+            }
+        }
+        lines
+    }
+
+    /// Renders the report as a JSON object with sizes in bytes
+    pub fn to_json(&self) -> Value {
+        let histogram: Vec<_> = self
+            .histogram
+            .iter()
+            .enumerate()
+            .filter(|(_, tally)| tally.files > 0)
+            .map(|(index, tally)| {
+                let (min, max) = bucket_range(index);
+                json!({ "min": min, "max": max, "files": tally.files, "bytes": tally.bytes })
+            })
+            .collect();
+        let extensions: Vec<_> = self
+            .sorted_extensions()
+            .into_iter()
+            .map(|(ext, tally)| json!({ "extension": ext, "files": tally.files, "bytes": tally.bytes }))
+            .collect();
+        let largest = |list: &[(PathBuf, u64)]| -> Vec<Value> {
+            list.iter()
+                .map(|(path, bytes)| json!({ "path": path.to_string_lossy(), "bytes": bytes }))
+                .collect()
+        };
+
+        json!({
+            "path": self.path.to_string_lossy(),
+            "files": self.files.files,
+            "bytes": self.files.bytes,
+            "allocated": self.allocated,
+            "directories": self.dirs,
+            "histogram": histogram,
+            "extensions": extensions,
+            "largest_files": largest(&self.largest_files),
+            "largest_directories": largest(&self.largest_dirs),
+        })
+    }
+}
+
+/// Lays out labelled tallies as aligned "files  bytes  label" rows;
+/// `label` renders the padded label and anything after it
+fn tally_rows(
+    rows: &[(String, Tally)],
+    unit: &BlockSize,
+    label: impl Fn(&str, Tally) -> String,
+) -> Vec<String> {
+    let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    let sizes: Vec<_> = rows
+        .iter()
+        .map(|(_, tally)| format_size(tally.bytes, unit).trim_start().to_string())
+        .collect();
+    let size_width = sizes.iter().map(String::len).max().unwrap_or(0);
+    let count_width = rows
+        .iter()
+        .map(|(_, tally)| tally.files.to_string().len())
+        .max()
+        .unwrap_or(0);
+
+    rows.iter()
+        .zip(&sizes)
+        .map(|((text, tally), size)| {
+            format!(
+                "  {}  {}  {}",
+                pad_left(&tally.files.to_string(), count_width),
+                pad_left(size, size_width),
+                label(&pad_right(text, label_width), *tally)
+            )
+        })
+        .collect()
+}
+
+/// Histogram bucket for a size: 0 for empty files, otherwise one more than
+/// the position of the highest set bit, so bucket k holds [2^(k-1), 2^k)
+fn bucket(size: u64) -> usize {
+    (u64::BITS - size.leading_zeros()) as usize
+}
+
+/// Smallest size in a bucket and the exclusive upper bound (None for the
+/// last bucket, which has no upper bound)
+fn bucket_range(index: usize) -> (u64, Option<u64>) {
+    match index {
+        0 => (0, Some(1)),
+        _ => (
+            1 << (index - 1),
+            1u64.checked_shl(u32::try_from(index).unwrap_or(u32::MAX)),
+        ),
+    }
+}
+
+/// Labels a bucket with its range in binary units, e.g. "4K..8K"
+fn bucket_label(index: usize) -> String {
+    let short = |bytes: u64| {
+        let unit = bytes.trailing_zeros().min(60) / 10;
+        let suffix = ["", "K", "M", "G", "T", "P", "E"][unit as usize];
+        format!("{}{suffix}", bytes >> (unit * 10))
+    };
+    match bucket_range(index) {
+        (0, _) => "0".to_string(),
+        (min, Some(max)) => format!("{}..{}", short(min), short(max)),
+        (min, None) => format!("{}..", short(min)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets() {
+        assert_eq!(bucket(0), 0);
+        assert_eq!(bucket(1), 1);
+        assert_eq!(bucket(1023), 10);
+        assert_eq!(bucket(1024), 11);
+        assert_eq!(bucket(u64::MAX), 64);
+        assert_eq!(bucket_range(11), (1024, Some(2048)));
+        assert_eq!(bucket_range(64), (1 << 63, None));
+        assert_eq!(bucket_label(0), "0");
+        assert_eq!(bucket_label(3), "4..8");
+        assert_eq!(bucket_label(10), "512..1K");
+        assert_eq!(bucket_label(13), "4K..8K");
+        assert_eq!(bucket_label(64), "8E..");
+    }
+}
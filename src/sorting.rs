@@ -4,32 +4,53 @@
 //! including alphabetical and time-based sorting with reverse options.
 
 use crate::file_info::FileInfo;
+use crate::utils::TimeField;
 use std::cmp::Ordering;
 
+/// What entries are ordered by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Name, // Alphabetically, ignoring case (default)
+    Time, // Newest first (-t)
+    Size, // Largest first (-S)
+}
+
 /// Configuration for sorting behavior
 pub struct SortConfig {
-    pub by_time: bool, // Sort by modification time instead of name
-    pub reverse: bool, // Reverse the sort order
+    pub key: SortKey,          // What to sort by
+    pub time_field: TimeField, // Which timestamp to sort by (--time-field)
+    pub reverse: bool,         // Reverse the sort order
 }
 
 impl SortConfig {
     /// Creates a new sort configuration from command-line arguments
-    pub fn new(by_time: bool, reverse: bool) -> Self {
-        SortConfig { by_time, reverse }
+    pub fn new(key: SortKey, time_field: TimeField, reverse: bool) -> Self {
+        SortConfig {
+            key,
+            time_field,
+            reverse,
+        }
     }
 }
 
 /// Sorts entries based on the provided configuration.
-/// Supports sorting by name (default) or modification time.
+/// Supports sorting by name (default), by a timestamp, or by size.
+/// Files without the timestamp (e.g., no birth time) sort as the oldest,
+/// and files of equal size are ordered by name.
 /// Can reverse the sort order.
 pub fn sort_entries(entries: &mut [FileInfo], config: &SortConfig) {
     entries.sort_by(|a, b| {
-        let cmp = if config.by_time {
-            // Sort by modification time (newest first)
-            b.modified_time().cmp(&a.modified_time())
-        } else {
+        let by_name = || a.file_name.to_lowercase().cmp(&b.file_name.to_lowercase());
+        let cmp = match config.key {
+            // Sort by time (newest first)
+            SortKey::Time => {
+                let field = config.time_field;
+                b.time(field).cmp(&a.time(field))
+            }
+            // Sort by size (largest first); directories use their tree size with --total-size
+            SortKey::Size => b.size().cmp(&a.size()).then_with(by_name),
             // Sort by name (case-insensitive)
-            a.file_name.to_lowercase().cmp(&b.file_name.to_lowercase())
+            SortKey::Name => by_name(),
         };
 
         // Apply reverse if requested
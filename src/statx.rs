@@ -0,0 +1,71 @@
+//! Extended file status via the Linux `statx` call.
+//!
+//! `std::fs::Metadata` covers the classic `stat` fields. `statx` adds the
+//! birth time, the mount id and the `STATX_ATTR_*` attribute flags. Older
+//! kernels and some file systems don't report these, so every field is
+//! optional and callers fall back to `Metadata` for everything else.
+
+use std::ffi::CString;
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Fields requested from the kernel
+const MASK: libc::c_uint = libc::STATX_BTIME | libc::STATX_MNT_ID;
+
+/// The statx fields that `Metadata` does not provide
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Statx {
+    pub birth_time: Option<SystemTime>, // Creation time, if the file system records it
+    pub mount_id: Option<u64>,          // Id of the mount containing the file
+    pub attributes: u64,                // STATX_ATTR_* flags the file system reports as set
+}
+
+/// Queries a path without following symlinks
+pub fn statx(path: &Path) -> io::Result<Statx> {
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    let mut buf = MaybeUninit::<libc::statx>::zeroed();
+
+    // SAFETY: `path` is NUL-terminated and `buf` is a writable statx struct
+    let result = unsafe {
+        libc::statx(
+            libc::AT_FDCWD,
+            path.as_ptr(),
+            libc::AT_SYMLINK_NOFOLLOW | libc::AT_STATX_SYNC_AS_STAT,
+            MASK,
+            buf.as_mut_ptr(),
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: statx succeeded, so the kernel filled in the struct (and it
+    // was zeroed beforehand in any case)
+    let buf = unsafe { buf.assume_init() };
+
+    let birth_time = (buf.stx_mask & libc::STATX_BTIME != 0)
+        .then(|| timestamp(buf.stx_btime.tv_sec, buf.stx_btime.tv_nsec))
+        .flatten();
+    let mount_id = (buf.stx_mask & libc::STATX_MNT_ID != 0).then_some(buf.stx_mnt_id);
+
+    Ok(Statx {
+        birth_time,
+        mount_id,
+        attributes: buf.stx_attributes & buf.stx_attributes_mask,
+    })
+}
+
+/// Converts seconds and nanoseconds since the epoch to a SystemTime,
+/// handling times before 1970
+pub fn timestamp(seconds: i64, nanos: u32) -> Option<SystemTime> {
+    let offset = Duration::from_secs(seconds.unsigned_abs());
+    let time = if seconds >= 0 {
+        SystemTime::UNIX_EPOCH.checked_add(offset)?
+    } else {
+        SystemTime::UNIX_EPOCH.checked_sub(offset)?
+    };
+    time.checked_add(Duration::from_nanos(u64::from(nanos)))
+}
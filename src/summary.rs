@@ -0,0 +1,96 @@
+//! Listing statistics for `--summary`.
+//!
+//! A Summary counts entries by type and adds up their sizes and modification
+//! times. One is printed after each directory listing, and a merged one for
+//! the whole run when several paths or a recursive listing were shown.
+
+use crate::file_info::FileInfo;
+use crate::formatter::FormatConfig;
+use crate::utils::{format_size, format_time, TimeField};
+use std::time::SystemTime;
+
+/// Counts and totals for a set of entries
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub files: u64,                 // Regular files
+    pub dirs: u64,                  // Directories
+    pub symlinks: u64,              // Symbolic links
+    pub special: u64,               // FIFOs, sockets and device files
+    pub apparent: u64,              // Sum of file sizes in bytes
+    pub allocated: u64,             // Sum of allocated sizes in bytes
+    pub newest: Option<SystemTime>, // Latest modification time
+    pub oldest: Option<SystemTime>, // Earliest modification time
+}
+
+impl Summary {
+    /// Adds one entry to the counts and totals
+    pub fn add(&mut self, file_info: &FileInfo) {
+        let file_type = file_info.file_type();
+        if file_type.is_dir() {
+            self.dirs += 1;
+        } else if file_type.is_symlink() {
+            self.symlinks += 1;
+        } else if file_type.is_file() {
+            self.files += 1;
+        } else {
+            self.special += 1;
+        }
+
+        self.apparent += file_info.size();
+        self.allocated += file_info.blocks() * 512;
+        if let Some(modified) = file_info.time(TimeField::Mtime) {
+            self.newest = self.newest.max(Some(modified));
+            self.oldest = Some(self.oldest.map_or(modified, |oldest| oldest.min(modified)));
+        }
+    }
+
+    /// Adds another summary's counts and totals to this one
+    pub fn merge(&mut self, other: &Summary) {
+        self.files += other.files;
+        self.dirs += other.dirs;
+        self.symlinks += other.symlinks;
+        self.special += other.special;
+        self.apparent += other.apparent;
+        self.allocated += other.allocated;
+        self.newest = self.newest.max(other.newest);
+        self.oldest = match (self.oldest, other.oldest) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+
+    /// Renders the summary as one line, e.g.
+    /// "3 files, 1 directory, 0 symlinks, 0 special; 5.2K apparent, 16K allocated;
+    /// newest Jan 15 10:30, oldest Jan 02 09:12".
+    /// Sizes use the long format size unit and times the configured time style.
+    pub fn render(&self, config: &FormatConfig) -> String {
+        let size = |bytes| {
+            format_size(bytes, &config.size_unit)
+                .trim_start()
+                .to_string()
+        };
+        let mut line = format!(
+            "{}, {}, {}, {} special; {} apparent, {} allocated",
+            plural(self.files, "file", "files"),
+            plural(self.dirs, "directory", "directories"),
+            plural(self.symlinks, "symlink", "symlinks"),
+            self.special,
+            size(self.apparent),
+            size(self.allocated),
+        );
+        if let (Some(newest), Some(oldest)) = (self.newest, self.oldest) {
+            let time = |t| format_time(t, config.now, config.time_style);
+            line.push_str(&format!(
+                "; newest {}, oldest {}",
+                time(newest),
+                time(oldest)
+            ));
+        }
+        line
+    }
+}
+
+/// Formats a count with the singular or plural noun
+pub fn plural(count: u64, one: &str, many: &str) -> String {
+    format!("{count} {}", if count == 1 { one } else { many })
+}
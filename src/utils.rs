@@ -3,22 +3,94 @@
 //! This module contains helper functions for formatting file permissions,
 //! sizes, times, and applying colors to file names based on their type.
 
+use crate::colors;
 use chrono::{DateTime, Local};
 use colored::{ColoredString, Colorize};
-use std::fs::Metadata;
-use std::os::unix::fs::{MetadataExt, PermissionsExt};
-use std::time::SystemTime;
+use std::fs::{FileType, Metadata};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+use unicode_width::UnicodeWidthChar;
 
-/// Converts Unix file permissions to the standard drwxrwxrwx format
-pub fn format_permissions(metadata: &Metadata) -> String {
-    let mode = metadata.permissions().mode();
-    let file_type = if metadata.is_dir() {
+/// Timestamp styles accepted by `--time-style`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum TimeStyle {
+    #[default]
+    Locale,   // "Jan 15 10:30"
+    Iso,      // "01-15 10:30"
+    LongIso,  // "2024-01-15 10:30"
+    FullIso,  // "2024-01-15 10:30:00.000000000 +0100"
+    Relative, // "3 minutes ago", "yesterday", "2 years ago"
+}
+
+impl TimeStyle {
+    /// Column width used to keep long format rows aligned
+    pub fn width(self) -> usize {
+        match self {
+            TimeStyle::Locale | TimeStyle::Iso => 12,
+            TimeStyle::LongIso => 16,
+            TimeStyle::FullIso => 35,
+            TimeStyle::Relative => 14,
+        }
+    }
+}
+
+/// Timestamps selectable with `--time-field`, for display and for sorting with -t
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum TimeField {
+    #[default]
+    #[value(alias = "modification")]
+    Mtime, // Last modification
+    #[value(alias = "access", alias = "use")]
+    Atime, // Last access
+    #[value(alias = "status")]
+    Ctime, // Last status change
+    #[value(alias = "creation")]
+    Birth, // Creation, where the file system records it
+}
+
+/// Which type indicators to append to file names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum IndicatorStyle {
+    #[default]
+    None,     // No indicators
+    Slash,    // "/" after directories (-p)
+    FileType, // All indicators except "*" (--file-type)
+    Classify, // "/", "*", "@", "|" and "=" (-F)
+}
+
+/// How long ago a file was modified, used to shade the date column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgeBucket {
+    Hour,  // Within the last hour (or in the future)
+    Day,   // Within the last day
+    Week,  // Within the last week
+    Month, // Within the last 30 days
+    Older, // Anything older
+}
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+const MONTH: u64 = 30 * DAY;
+const YEAR: u64 = 365 * DAY;
+
+/// Returns the file type character that starts the permissions string
+pub fn file_type_char(file_type: FileType) -> char {
+    if file_type.is_dir() {
         'd'
-    } else if metadata.is_symlink() {
+    } else if file_type.is_symlink() {
         'l'
     } else {
         '-'
-    };
+    }
+}
+
+/// Converts Unix file permissions to the standard drwxrwxrwx format
+pub fn format_permissions(metadata: &Metadata) -> String {
+    let mode = metadata.permissions().mode();
+    let file_type = file_type_char(metadata.file_type());
 
     let user = triplet(mode >> 6);
     let group = triplet(mode >> 3);
@@ -35,58 +107,311 @@ fn triplet(mode: u32) -> String {
     format!("{r}{w}{x}")
 }
 
-/// Formats a system time as a human-readable date string (e.g., "Jan 15 10:30")
-pub fn format_time(system_time: SystemTime) -> String {
+/// Formats a system time according to the given style.
+/// `now` is passed in rather than read here so relative output stays testable.
+pub fn format_time(system_time: SystemTime, now: SystemTime, style: TimeStyle) -> String {
     let datetime: DateTime<Local> = system_time.into();
-    datetime.format("%b %d %H:%M").to_string()
+    match style {
+        TimeStyle::Locale => datetime.format("%b %d %H:%M").to_string(),
+        TimeStyle::Iso => datetime.format("%m-%d %H:%M").to_string(),
+        TimeStyle::LongIso => datetime.format("%Y-%m-%d %H:%M").to_string(),
+        TimeStyle::FullIso => datetime.format("%Y-%m-%d %H:%M:%S%.9f %z").to_string(),
+        TimeStyle::Relative => format_relative(system_time, now),
+    }
 }
 
-/// Converts byte size to human-readable format (B, K, M, G, T, P)
+/// Describes the distance between two times in words (e.g., "3 minutes ago")
+fn format_relative(system_time: SystemTime, now: SystemTime) -> String {
+    let (elapsed, future) = match now.duration_since(system_time) {
+        Ok(elapsed) => (elapsed, false),
+        Err(e) => (e.duration(), true),
+    };
+    let secs = elapsed.as_secs();
+
+    if secs < MINUTE {
+        return "just now".to_string();
+    }
+    if (DAY..2 * DAY).contains(&secs) {
+        return if future { "tomorrow" } else { "yesterday" }.to_string();
+    }
+
+    let (count, unit) = if secs < HOUR {
+        (secs / MINUTE, "minute")
+    } else if secs < DAY {
+        (secs / HOUR, "hour")
+    } else if secs < WEEK {
+        (secs / DAY, "day")
+    } else if secs < MONTH {
+        (secs / WEEK, "week")
+    } else if secs < YEAR {
+        (secs / MONTH, "month")
+    } else {
+        (secs / YEAR, "year")
+    };
+    let plural = if count == 1 { "" } else { "s" };
+
+    if future {
+        format!("in {count} {unit}{plural}")
+    } else {
+        format!("{count} {unit}{plural} ago")
+    }
+}
+
+/// Places a timestamp into an age bucket relative to `now`
+pub fn age_bucket(system_time: SystemTime, now: SystemTime) -> AgeBucket {
+    let age = now.duration_since(system_time).unwrap_or(Duration::ZERO);
+    match age.as_secs() {
+        s if s < HOUR => AgeBucket::Hour,
+        s if s < DAY => AgeBucket::Day,
+        s if s < WEEK => AgeBucket::Week,
+        s if s < MONTH => AgeBucket::Month,
+        _ => AgeBucket::Older,
+    }
+}
+
+/// Shades a formatted timestamp by age: brighter is more recent
+pub fn colorize_time(text: &str, bucket: AgeBucket) -> ColoredString {
+    match bucket {
+        AgeBucket::Hour => text.bright_green(),
+        AgeBucket::Day => text.green(),
+        AgeBucket::Week => text.yellow(),
+        AgeBucket::Month => text.bright_black(),
+        AgeBucket::Older => text.dimmed(),
+    }
+}
+
+/// Unit used when printing sizes, as chosen by -h, --si, -k and --block-size
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockSize {
+    Human(u64), // Scale to the largest fitting unit in powers of the base (1024 or 1000)
+    Fixed(u64, String), // Count in units of this many bytes, rounding up, followed by the suffix
+}
+
+impl BlockSize {
+    /// One-byte units: exact sizes, as shown by default in long format
+    pub fn bytes() -> Self {
+        BlockSize::Fixed(1, String::new())
+    }
+
+    /// 1024-byte units, the default for -s and the long format total
+    pub fn kibibytes() -> Self {
+        BlockSize::Fixed(1024, String::new())
+    }
+}
+
+impl FromStr for BlockSize {
+    type Err = String;
+
+    /// Parses a GNU-style block size: `human-readable`, `si`, or an optional
+    /// count followed by an optional unit (`K`, `KiB`, `KB`, `1M`, `512`).
+    /// Units ending in `B` are powers of 1000, others powers of 1024.
+    /// A unit given without a count is printed as a suffix after each size.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human-readable" => return Ok(BlockSize::Human(1024)),
+            "si" => return Ok(BlockSize::Human(1000)),
+            _ => {}
+        }
+
+        let invalid = || format!("invalid block size: '{s}'");
+        let digits_end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (count, unit) = s.split_at(digits_end);
+        let count = if count.is_empty() {
+            1
+        } else {
+            count.parse::<u64>().map_err(|_| invalid())?
+        };
+
+        let multiplier = if unit.is_empty() {
+            1
+        } else {
+            let mut chars = unit.chars();
+            let prefix = chars.next().unwrap_or_default().to_ascii_uppercase();
+            let exponent = "KMGTPE".find(prefix).ok_or_else(invalid)? + 1;
+            let base: u64 = match chars.as_str() {
+                "" | "iB" => 1024,
+                "B" => 1000,
+                _ => return Err(invalid()),
+            };
+            base.checked_pow(u32::try_from(exponent).map_err(|_| invalid())?)
+                .ok_or_else(invalid)?
+        };
+
+        let bytes = count.checked_mul(multiplier).ok_or_else(invalid)?;
+        if bytes == 0 {
+            return Err(invalid());
+        }
+
+        let suffix = if digits_end == 0 {
+            unit.to_string()
+        } else {
+            String::new()
+        };
+        Ok(BlockSize::Fixed(bytes, suffix))
+    }
+}
+
+/// Converts byte size to human-readable format (B, K, M, G, T, P).
+/// A base of 1000 (--si) uses a lowercase `k` as GNU ls does.
 #[allow(
     clippy::cast_precision_loss,
     clippy::cast_possible_truncation,
     clippy::cast_sign_loss
 )]
-pub fn format_size_human(size: u64) -> String {
-    const UNITS: &[&str] = &["", "K", "M", "G", "T", "P"];
+pub fn format_size_human(size: u64, base: u64) -> String {
+    let units: &[&str] = if base == 1000 {
+        &["", "k", "M", "G", "T", "P"]
+    } else {
+        &["", "K", "M", "G", "T", "P"]
+    };
+    let base = base as f64;
     let mut size = size as f64;
     let mut unit_index = 0;
 
-    while size >= 1024.0 && unit_index < UNITS.len() - 1 {
-        size /= 1024.0;
+    while size >= base && unit_index < units.len() - 1 {
+        size /= base;
         unit_index += 1;
     }
 
     if unit_index == 0 {
         format!("{:>4}", size as u64)
     } else if size >= 10.0 {
-        format!("{:>3.0}{}", size, UNITS[unit_index])
+        format!("{:>3.0}{}", size, units[unit_index])
     } else {
-        format!("{:>3.1}{}", size, UNITS[unit_index])
+        format!("{:>3.1}{}", size, units[unit_index])
     }
 }
 
-/// Formats block size for the -s flag, handling platform differences
-pub fn format_block_size(metadata: &Metadata) -> String {
-    #[cfg(unix)]
-    {
-        format!("{:>8}", (metadata.blocks() * 512).div_ceil(1024))
+/// Formats a byte count in the given unit
+pub fn format_size(size: u64, unit: &BlockSize) -> String {
+    match unit {
+        BlockSize::Human(base) => format_size_human(size, *base),
+        BlockSize::Fixed(bytes, suffix) => format!("{}{suffix}", size.div_ceil(*bytes)),
     }
-    #[cfg(not(unix))]
-    {
-        // Fallback for non-Unix: show size in KB
-        format!("{:>8}", metadata.len().div_ceil(1024))
+}
+
+/// Formats an allocated size in bytes for the -s flag in the given unit
+pub fn format_block_size(allocated: u64, unit: &BlockSize) -> String {
+    format!("{:>8}", format_size(allocated, unit))
+}
+
+/// Returns the indicator character for a file type, if the style calls for one:
+/// `/` directory, `*` executable, `@` symlink, `|` FIFO, `=` socket.
+/// `mode` is only called for regular files with -F, so other entries
+/// never need their full metadata.
+pub fn indicator(
+    file_type: FileType,
+    mode: impl FnOnce() -> u32,
+    style: IndicatorStyle,
+) -> Option<char> {
+    if style == IndicatorStyle::None {
+        None
+    } else if file_type.is_dir() {
+        Some('/')
+    } else if style == IndicatorStyle::Slash {
+        None
+    } else if file_type.is_symlink() {
+        Some('@')
+    } else if file_type.is_fifo() {
+        Some('|')
+    } else if file_type.is_socket() {
+        Some('=')
+    } else if style == IndicatorStyle::Classify && file_type.is_file() && mode() & 0o111 != 0 {
+        Some('*')
+    } else {
+        None
     }
 }
 
-/// Applies color to filename based on file type and permissions
-pub fn colorize_name(name: &str, metadata: &Metadata) -> ColoredString {
-    let mode = metadata.permissions().mode();
+/// Returns the number of terminal columns a string occupies, counting
+/// wide (CJK, emoji) characters as two, combining marks as zero, and
+/// ignoring ANSI color escape sequences
+pub fn display_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip a CSI sequence such as "\x1b[1;34m" up to its final letter
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            width += c.width().unwrap_or(0);
+        }
+    }
+    width
+}
+
+/// Right-aligns text within `width` terminal columns
+pub fn pad_left(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(display_width(text));
+    format!("{}{text}", " ".repeat(padding))
+}
+
+/// Left-aligns text within `width` terminal columns
+pub fn pad_right(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(display_width(text));
+    format!("{text}{}", " ".repeat(padding))
+}
+
+/// Shortens plain (uncolored) text to at most `max_width` terminal columns,
+/// replacing the cut-off tail with an ellipsis (nothing fits in 0 columns)
+pub fn truncate_to_width(text: &str, max_width: usize) -> String {
+    if display_width(text) <= max_width {
+        return text.to_string();
+    }
+    if max_width == 0 {
+        return String::new();
+    }
+
+    let mut truncated = String::new();
+    let mut width = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if width + char_width + 1 > max_width {
+            break;
+        }
+        truncated.push(c);
+        width += char_width;
+    }
+    truncated.push('…');
+    truncated
+}
 
-    if metadata.is_dir() {
+/// Returns the width of the terminal on stdout, falling back to the
+/// COLUMNS environment variable and then to 80 columns
+pub fn terminal_width() -> usize {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: TIOCGWINSZ only writes a winsize struct through the pointer we pass
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    if result == 0 && size.ws_col > 0 {
+        return usize::from(size.ws_col);
+    }
+
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .filter(|&columns| columns > 0)
+        .unwrap_or(80)
+}
+
+/// Applies color to filename based on file type and permissions.
+/// `mode` is not called for directories, which are colored by type alone.
+pub fn colorize_name(name: &str, file_type: FileType, mode: impl FnOnce() -> u32) -> ColoredString {
+    if file_type.is_dir() {
         // Directories are blue
-        name.blue()
-    } else if mode & 0o111 != 0 {
+        return name.blue();
+    }
+
+    let mode = mode();
+    if mode & 0o111 != 0 {
         // Executable files (user, group, or other) are red
         name.red()
     } else if mode & 0o004 != 0 {
@@ -97,3 +422,94 @@ pub fn colorize_name(name: &str, metadata: &Metadata) -> ColoredString {
         name.white()
     }
 }
+
+/// Colors the name of a file that carries capabilities, from the `ca` key
+/// in LS_COLORS; black on red by default, as in GNU ls
+pub fn colorize_capability(name: &str) -> ColoredString {
+    colors::paint("ca", name).unwrap_or_else(|| name.black().on_red())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width("main.rs"), 7);
+        assert_eq!(display_width("日本語.txt"), 10);
+        assert_eq!(display_width("e\u{301}"), 1); // "e" + combining acute accent
+        assert_eq!(display_width("\x1b[34mdir\x1b[0m"), 3);
+        assert_eq!(pad_left("日本", 6), "  日本");
+        assert_eq!(pad_right("日本", 6), "日本  ");
+    }
+
+    #[test]
+    fn test_truncate_to_width() {
+        assert_eq!(truncate_to_width("short", 10), "short");
+        assert_eq!(truncate_to_width("a-long-name", 6), "a-lon…");
+        // A wide character that would straddle the limit is dropped whole
+        assert_eq!(truncate_to_width("ab日本語", 5), "ab日…");
+        assert_eq!(truncate_to_width("abc", 0), "");
+    }
+
+    #[test]
+    fn test_parse_block_size() {
+        let fixed = |bytes, suffix: &str| Ok(BlockSize::Fixed(bytes, suffix.to_string()));
+        assert_eq!("K".parse(), fixed(1024, "K"));
+        assert_eq!("KiB".parse(), fixed(1024, "KiB"));
+        assert_eq!("MB".parse(), fixed(1_000_000, "MB"));
+        assert_eq!("1M".parse(), fixed(1_048_576, ""));
+        assert_eq!("512".parse(), fixed(512, ""));
+        assert_eq!("si".parse(), Ok(BlockSize::Human(1000)));
+        assert!("0".parse::<BlockSize>().is_err());
+        assert!("3X".parse::<BlockSize>().is_err());
+        assert!("KQ".parse::<BlockSize>().is_err());
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(1500, &BlockSize::Human(1024)), "1.5K");
+        assert_eq!(format_size(1500, &BlockSize::Human(1000)), "1.5k");
+        assert_eq!(format_size(1500, &BlockSize::Fixed(1024, "K".into())), "2K");
+        assert_eq!(format_size(1500, &BlockSize::bytes()), "1500");
+    }
+
+    fn ago(now: SystemTime, secs: u64) -> String {
+        format_time(now - Duration::from_secs(secs), now, TimeStyle::Relative)
+    }
+
+    #[test]
+    fn test_format_relative() {
+        let now = SystemTime::now();
+        assert_eq!(ago(now, 10), "just now");
+        assert_eq!(ago(now, 3 * MINUTE), "3 minutes ago");
+        assert_eq!(ago(now, HOUR), "1 hour ago");
+        assert_eq!(ago(now, DAY + HOUR), "yesterday");
+        assert_eq!(ago(now, 3 * DAY), "3 days ago");
+        assert_eq!(ago(now, 2 * WEEK), "2 weeks ago");
+        assert_eq!(ago(now, 2 * YEAR), "2 years ago");
+        assert_eq!(
+            format_time(
+                now + Duration::from_secs(5 * MINUTE),
+                now,
+                TimeStyle::Relative
+            ),
+            "in 5 minutes"
+        );
+    }
+
+    #[test]
+    fn test_age_bucket() {
+        let now = SystemTime::now();
+        let at = |secs| age_bucket(now - Duration::from_secs(secs), now);
+        assert_eq!(at(0), AgeBucket::Hour);
+        assert_eq!(at(2 * HOUR), AgeBucket::Day);
+        assert_eq!(at(2 * DAY), AgeBucket::Week);
+        assert_eq!(at(2 * WEEK), AgeBucket::Month);
+        assert_eq!(at(2 * MONTH), AgeBucket::Older);
+        assert_eq!(
+            age_bucket(now + Duration::from_secs(60), now),
+            AgeBucket::Hour
+        );
+    }
+}
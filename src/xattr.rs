@@ -0,0 +1,119 @@
+//! Extended attribute access.
+//!
+//! Thin wrappers around the Linux `llistxattr` and `lgetxattr` calls. Symlinks
+//! are never followed, so a link reports its own attributes, not its target's.
+
+use std::ffi::{CString, OsStr, OsString};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
+
+/// Number of value bytes shown by the --xattr preview
+const PREVIEW_BYTES: usize = 32;
+
+/// Converts a path to a C string for the system calls
+fn c_path(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes()).map_err(|_| io::ErrorKind::InvalidInput.into())
+}
+
+/// Returns true for errors meaning "this file system has no attributes here",
+/// which are reported as an empty list rather than a failure
+fn is_unsupported(error: &io::Error) -> bool {
+    matches!(
+        error.raw_os_error(),
+        Some(libc::ENOTSUP | libc::ENODATA | libc::ENOSYS)
+    )
+}
+
+/// Runs a size-probing xattr call twice: once to learn the value length, and
+/// again with a buffer of that size, retrying if the value grew in between
+fn read_sized(mut call: impl FnMut(*mut libc::c_void, usize) -> isize) -> io::Result<Vec<u8>> {
+    loop {
+        let len = call(ptr::null_mut(), 0);
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut buf = vec![0u8; len.unsigned_abs()];
+        let len = call(buf.as_mut_ptr().cast(), buf.len());
+        if len >= 0 {
+            buf.truncate(len.unsigned_abs());
+            return Ok(buf);
+        }
+
+        let error = io::Error::last_os_error();
+        if error.raw_os_error() != Some(libc::ERANGE) {
+            return Err(error);
+        }
+    }
+}
+
+/// Lists the names of all extended attributes on a file
+pub fn list(path: &Path) -> io::Result<Vec<OsString>> {
+    let path = c_path(path)?;
+    // SAFETY: `path` is NUL-terminated and `buf` is valid for `size` bytes (or null with size 0)
+    let names =
+        read_sized(|buf, size| unsafe { libc::llistxattr(path.as_ptr(), buf.cast(), size) });
+
+    match names {
+        Ok(names) => Ok(names
+            .split(|&b| b == 0)
+            .filter(|name| !name.is_empty())
+            .map(|name| OsStr::from_bytes(name).to_os_string())
+            .collect()),
+        Err(e) if is_unsupported(&e) => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Reads the value of one extended attribute.
+/// Returns `Ok(None)` if the attribute does not exist.
+pub fn get(path: &Path, name: &OsStr) -> io::Result<Option<Vec<u8>>> {
+    let path = c_path(path)?;
+    let name =
+        CString::new(name.as_bytes()).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    // SAFETY: both strings are NUL-terminated and `buf` is valid for `size` bytes (or null with size 0)
+    let value =
+        read_sized(|buf, size| unsafe { libc::lgetxattr(path.as_ptr(), name.as_ptr(), buf, size) });
+
+    match value {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if is_unsupported(&e) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Renders the start of an attribute value: quoted text when it is printable
+/// UTF-8, hex bytes otherwise. Long values are cut off with "...".
+pub fn preview(value: &[u8]) -> String {
+    let shown = &value[..value.len().min(PREVIEW_BYTES)];
+    let more = if value.len() > PREVIEW_BYTES {
+        "..."
+    } else {
+        ""
+    };
+    // Text values are often stored with a trailing NUL
+    let text = shown.strip_suffix(&[0]).unwrap_or(shown);
+
+    match std::str::from_utf8(text) {
+        Ok(text) if !text.chars().any(char::is_control) => format!("\"{text}\"{more}"),
+        _ => {
+            let hex: Vec<String> = shown.iter().map(|b| format!("{b:02x}")).collect();
+            format!("{}{more}", hex.join(" "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_text_and_binary() {
+        assert_eq!(preview(b"text/plain"), "\"text/plain\"");
+        assert_eq!(preview(b"label\0"), "\"label\"");
+        assert_eq!(preview(&[0x01, 0x00, 0xff]), "01 00 ff");
+        assert_eq!(preview(&[b'a'; 40]), format!("\"{}\"...", "a".repeat(32)));
+    }
+}
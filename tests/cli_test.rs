@@ -2,6 +2,7 @@
 use assert_cmd::Command;
 use predicates::prelude::*;
 use std::fs::{self, File};
+use std::os::unix::fs::MetadataExt;
 use tempfile::TempDir;
 
 /// Test basic directory listing
@@ -185,4 +186,626 @@ fn test_human_readable_sizes() {
         .success()
         .stdout(predicate::str::contains("test.txt"))
         .stdout(predicate::str::is_match(r"\d+\.\d+K|\d+K").unwrap());
-}
\ No newline at end of file
+}
+
+/// Test --block-size scaling in long format and the total line
+#[test]
+fn test_block_size_option() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("data.bin"), vec![0u8; 5000]).unwrap();
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-l").arg("--block-size=K").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"^total \d+K\n").unwrap())
+        .stdout(predicate::str::is_match(r"\s5K \w{3} .*data\.bin").unwrap());
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-l").arg("--si").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("5.0k"));
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--block-size=0").arg(temp_dir.path());
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid block size"));
+}
+
+/// Test inode column (-i) and numeric ids (-n)
+#[test]
+fn test_inode_and_numeric_ids() {
+    use std::os::unix::fs::MetadataExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    let file_path = temp_dir.path().join("file.txt");
+    File::create(&file_path).unwrap();
+    let metadata = fs::metadata(&file_path).unwrap();
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-i").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!("{} file.txt", metadata.ino())));
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-n").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            " {} {} ",
+            metadata.uid(),
+            metadata.gid()
+        )));
+}
+
+/// Test dropping owner (-g) and group (-o) columns
+#[test]
+fn test_hide_owner_and_group() {
+    let temp_dir = TempDir::new().unwrap();
+    File::create(temp_dir.path().join("file.txt")).unwrap();
+
+    let full = Command::cargo_bin("ls-rust")
+        .unwrap()
+        .arg("-l")
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+    let no_owner = Command::cargo_bin("ls-rust")
+        .unwrap()
+        .arg("-g")
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+    let no_both = Command::cargo_bin("ls-rust")
+        .unwrap()
+        .arg("-go")
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+
+    let fields = |out: &[u8]| {
+        String::from_utf8_lossy(out)
+            .lines()
+            .nth(1)
+            .unwrap()
+            .split_whitespace()
+            .count()
+    };
+    assert_eq!(fields(&no_owner.stdout), fields(&full.stdout) - 1);
+    assert_eq!(fields(&no_both.stdout), fields(&full.stdout) - 2);
+}
+
+/// Test NUL-terminated output (--zero)
+#[test]
+fn test_zero_terminated_output() {
+    let temp_dir = TempDir::new().unwrap();
+    File::create(temp_dir.path().join("a b.txt")).unwrap();
+    File::create(temp_dir.path().join("line\nbreak")).unwrap();
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--zero").arg("--quoting-style=c").arg(temp_dir.path());
+
+    // Quoting is disabled so names come through byte for byte
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("a b.txt\0line\nbreak\0"));
+}
+
+/// Test --zero with -R emits full paths instead of directory headers
+#[test]
+fn test_zero_recursive_full_paths() {
+    let temp_dir = TempDir::new().unwrap();
+    let sub_dir = temp_dir.path().join("sub");
+    fs::create_dir(&sub_dir).unwrap();
+    File::create(sub_dir.join("inner.txt")).unwrap();
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--zero").arg("-R").arg(temp_dir.path());
+
+    let expected = format!(
+        "{}\0{}\0",
+        sub_dir.display(),
+        sub_dir.join("inner.txt").display()
+    );
+    cmd.assert().success().stdout(predicate::eq(expected));
+}
+
+/// Test sorting and showing birth time (--time-field=birth) rather than mtime
+#[test]
+fn test_sort_by_birth_time() {
+    let temp_dir = TempDir::new().unwrap();
+    let older = File::create(temp_dir.path().join("older")).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    File::create(temp_dir.path().join("newer")).unwrap();
+    // Give the older file the newest modification time
+    older
+        .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))
+        .unwrap();
+
+    for flag in ["-t", "--time"] {
+        let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+        cmd.arg(flag).arg(temp_dir.path());
+
+        cmd.assert().success().stdout(predicate::eq("older\nnewer\n"));
+    }
+
+    // Not every file system records birth times
+    if older.metadata().unwrap().created().is_err() {
+        return;
+    }
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-t").arg("--time-field=birth").arg(temp_dir.path());
+
+    cmd.assert().success().stdout(predicate::eq("newer\nolder\n"));
+}
+
+/// Test that plain listings don't stat entries, and that long format shows
+/// '?' for entries whose metadata can't be read (directory without search permission)
+#[test]
+fn test_unreadable_metadata() {
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Makes the directory searchable again, even if an assertion fails
+    struct Restore(std::path::PathBuf);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let _ = fs::set_permissions(&self.0, fs::Permissions::from_mode(0o755));
+        }
+    }
+
+    // Root can stat the entries anyway, so there is nothing to check
+    if unsafe { libc::geteuid() } == 0 {
+        return;
+    }
+
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path().join("locked");
+    fs::create_dir(&dir).unwrap();
+    File::create(dir.join("file")).unwrap();
+    fs::set_permissions(&dir, fs::Permissions::from_mode(0o444)).unwrap();
+    let _restore = Restore(dir.clone());
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg(&dir);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("file\n"))
+        .stderr(predicate::str::is_empty());
+
+    // The error is reported once, however many columns needed the metadata
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-l").arg(&dir);
+
+    cmd.assert()
+        .stdout(predicate::str::contains("-?????????   ? ? ?        ? ?            file"))
+        .stderr(predicate::str::contains("cannot access").count(1));
+}
+
+/// Test that --id-source=none prints numeric ids, like -n, and that
+/// --where sees the same numeric owner
+#[test]
+fn test_id_source_none() {
+    let temp_dir = TempDir::new().unwrap();
+    File::create(temp_dir.path().join("file.txt")).unwrap();
+
+    let numeric = Command::cargo_bin("ls-rust")
+        .unwrap()
+        .arg("-ln")
+        .arg(temp_dir.path())
+        .output()
+        .unwrap();
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-l").arg("--id-source=none").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq(String::from_utf8(numeric.stdout).unwrap()));
+
+    let uid = fs::metadata(temp_dir.path().join("file.txt")).unwrap().uid();
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--id-source=none")
+        .arg("--where")
+        .arg(format!(r#"user == "{uid}""#))
+        .arg(temp_dir.path());
+    cmd.assert().success().stdout("file.txt\n");
+}
+
+/// Test directory tree sizes (--total-size), counting hard links once, and sorting by size (-S)
+#[test]
+fn test_total_size_and_size_sort() {
+    let temp_dir = TempDir::new().unwrap();
+    let big = temp_dir.path().join("big");
+    fs::create_dir_all(big.join("sub")).unwrap();
+    fs::write(big.join("sub/data"), vec![0u8; 100_000]).unwrap();
+    fs::hard_link(big.join("sub/data"), big.join("link")).unwrap();
+    fs::write(temp_dir.path().join("file"), "abc").unwrap();
+
+    let dir_size = |path: &std::path::Path| fs::metadata(path).unwrap().len();
+    let expected = dir_size(&big) + dir_size(&big.join("sub")) + 100_000;
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-l").arg("--total-size").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(format!(" {expected} ")));
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-S").arg("--total-size").arg(temp_dir.path());
+
+    cmd.assert().success().stdout(predicate::eq("big\nfile\n"));
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-Sr").arg("--total-size").arg(temp_dir.path());
+
+    cmd.assert().success().stdout(predicate::eq("file\nbig\n"));
+}
+
+/// Test per-directory and overall statistics (--summary)
+#[test]
+fn test_summary() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("a"), "abc").unwrap();
+    fs::write(temp_dir.path().join("b"), "de").unwrap();
+    fs::create_dir(temp_dir.path().join("sub")).unwrap();
+    fs::write(temp_dir.path().join("sub/c"), "f").unwrap();
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--summary").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "summary: 2 files, 1 directory, 0 symlinks, 0 special;",
+        ))
+        .stdout(predicate::str::contains("overall:").not());
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-R").arg("--summary").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "summary: 1 file, 0 directories, 0 symlinks, 0 special; 1 apparent,",
+        ))
+        .stdout(predicate::str::contains(
+            "\n\noverall: 3 files, 1 directory, 0 symlinks, 0 special;",
+        ));
+}
+
+/// Test the capacity report (--report) in text and JSON
+#[test]
+fn test_report() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("empty.txt"), "").unwrap();
+    fs::write(temp_dir.path().join("small.TXT"), "abc").unwrap();
+    fs::create_dir(temp_dir.path().join("sub")).unwrap();
+    fs::write(temp_dir.path().join("sub/big.bin"), vec![0u8; 5000]).unwrap();
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--report").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("2 files, 3 apparent,"))
+        .stdout(predicate::str::contains("size histogram:\n  1  0  0     #"))
+        .stdout(predicate::str::contains("  1  3  2..4  #"))
+        .stdout(predicate::str::contains("  2  3  txt"));
+
+    // Path headers follow --quoting-style like listing headers
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.args(["--report", "--quoting-style=c", "sub", "sub"])
+        .current_dir(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("\"sub\":\n"));
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-R").arg("--report=json").arg("--top=1").arg(temp_dir.path());
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(report["files"], 3);
+    assert_eq!(report["bytes"], 5003);
+    assert_eq!(
+        report["histogram"][2],
+        serde_json::json!({ "min": 4096, "max": 8192, "files": 1, "bytes": 5000 })
+    );
+    assert_eq!(report["extensions"][0]["extension"], "bin");
+    assert_eq!(report["largest_files"].as_array().unwrap().len(), 1);
+    assert!(report["largest_files"][0]["path"]
+        .as_str()
+        .unwrap()
+        .ends_with("sub/big.bin"));
+}
+
+/// Test entry filters (--type, --size, --perm, --mtime, --owner), combined
+/// with AND semantics and applied at every level of -R
+#[test]
+fn test_entry_filters() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("big.bin"), vec![0u8; 20_000]).unwrap();
+    fs::write(temp_dir.path().join("small.txt"), "abc").unwrap();
+    fs::write(temp_dir.path().join("run.sh"), "#!/bin/sh\n").unwrap();
+    fs::set_permissions(
+        temp_dir.path().join("run.sh"),
+        fs::Permissions::from_mode(0o755),
+    )
+    .unwrap();
+    fs::create_dir(temp_dir.path().join("sub")).unwrap();
+    fs::write(temp_dir.path().join("sub/deep.txt"), "de").unwrap();
+
+    let ls = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+        cmd.args(args).arg(temp_dir.path());
+        String::from_utf8(cmd.assert().success().get_output().stdout.clone()).unwrap()
+    };
+
+    assert_eq!(ls(&["--type=d"]), "sub\n");
+    assert_eq!(ls(&["--type=f", "--size=+10k"]), "big.bin\n");
+    assert_eq!(ls(&["--size=-11", "--type=f"]), "run.sh\nsmall.txt\n");
+    assert_eq!(ls(&["--perm=-u+x", "--type=f"]), "run.sh\n");
+    assert_eq!(ls(&["--mtime=+1", "--type=f"]), "");
+    assert_eq!(ls(&["--owner=0", "--mtime=-1d", "--size=3"]), "small.txt\n");
+
+    // Filtered-out directories are still descended into
+    let recursive = ls(&["-R", "--type=f", "--size=-5"]);
+    assert!(recursive.starts_with("small.txt\n"));
+    assert!(recursive.contains("sub:\ndeep.txt\n"));
+    assert!(!recursive.contains("\nsub\n"));
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--size=12Q").arg(temp_dir.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid size: '12Q'"));
+
+    // Without '=', --size is the -s flag and what follows is parsed on its own
+    assert_eq!(ls(&["--size", "-1"]), ls(&["-s1"]));
+    assert_eq!(ls(&["--size", "-1k"]), ls(&["-s1k"]));
+}
+
+/// Test query expressions (--where) and their column-pointing errors
+#[test]
+fn test_where_expression() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("app.log"), vec![b'x'; 3000]).unwrap();
+    fs::write(temp_dir.path().join("notes.TXT"), "hi").unwrap();
+    fs::write(temp_dir.path().join("main.rs"), vec![b'x'; 5000]).unwrap();
+    fs::create_dir(temp_dir.path().join("logs")).unwrap();
+
+    let ls = |expr: &str| {
+        let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+        cmd.arg("--where").arg(expr).arg(temp_dir.path());
+        String::from_utf8(cmd.assert().success().get_output().stdout.clone()).unwrap()
+    };
+
+    assert_eq!(ls(r#"ext in ["log", "txt"]"#), "app.log\nnotes.TXT\n");
+    assert_eq!(ls(r#"size > 2k and type == "f""#), "app.log\nmain.rs\n");
+    assert_eq!(ls(r#"name matches "*.rs" or type == "d""#), "logs\nmain.rs\n");
+    assert_eq!(ls(r#"name =~ "^[a-m]" and not (size > 4K)"#), "app.log\nlogs\n");
+    assert_eq!(ls("mtime > now - 1h and mtime < now + 1d"), "app.log\nlogs\nmain.rs\nnotes.TXT\n");
+    assert_eq!(ls("mtime < now - 30d"), "");
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--where").arg("size > 1M and mtime < 30d").arg(temp_dir.path());
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "expected a time but found a duration at column 23\n  size > 1M and mtime < 30d\n                        ^",
+    ));
+}
+
+/// Test name patterns (--match, --regex, --exclude, their case-insensitive
+/// variants and --full-path), including across -R
+#[test]
+fn test_name_patterns() {
+    let temp_dir = TempDir::new().unwrap();
+    for name in ["main.rs", "lib.RS", "notes.md", "sub/mod.rs", "sub/README.md"] {
+        let path = temp_dir.path().join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(path).unwrap();
+    }
+
+    let ls = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+        cmd.args(args).arg(temp_dir.path());
+        String::from_utf8(cmd.assert().success().get_output().stdout.clone()).unwrap()
+    };
+
+    assert_eq!(ls(&["--match=*.rs"]), "main.rs\n");
+    assert_eq!(ls(&["--imatch=*.rs"]), "lib.RS\nmain.rs\n");
+    assert_eq!(ls(&["--match=*.rs", "--regex=^n"]), "main.rs\nnotes.md\n");
+    assert_eq!(ls(&["--iregex=\\.rs$", "--exclude=m*"]), "lib.RS\n");
+    assert_eq!(ls(&["--exclude-regex=\\.", "--iexclude=SUB"]), "");
+
+    let recursive = ls(&["-R", "--match=*.rs"]);
+    assert!(recursive.starts_with("main.rs\n\n"));
+    assert!(recursive.ends_with("sub:\nmod.rs\n"));
+
+    let full_path = ls(&["-R", "--full-path", "--match=sub/*"]);
+    assert!(!full_path.contains("main.rs"));
+    assert!(full_path.ends_with("sub:\nmod.rs\nREADME.md\n"));
+}
+
+/// Test flat recursive listings (--flat) with filters and tree-wide sorting
+#[test]
+fn test_flat_listing() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::create_dir_all(temp_dir.path().join("src/bin")).unwrap();
+    fs::write(temp_dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+    fs::write(temp_dir.path().join("src/bin/tool.rs"), vec![b'x'; 5000]).unwrap();
+    fs::write(temp_dir.path().join("README.md"), "# readme").unwrap();
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--flat").current_dir(temp_dir.path());
+
+    cmd.assert().success().stdout(predicate::eq(
+        "README.md\nsrc\nsrc/bin\nsrc/bin/tool.rs\nsrc/main.rs\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--flat").arg("-S").arg("--type=f").arg(temp_dir.path());
+
+    cmd.assert().success().stdout(predicate::eq(
+        "src/bin/tool.rs\nsrc/main.rs\nREADME.md\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--flat").arg("-l").arg("--match=*.rs").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("total "))
+        .stdout(predicate::str::contains(" src/bin/tool.rs\n"))
+        .stdout(predicate::str::contains("README").not());
+}
+
+/// Test directory visiting order (--walk) and unsorted output as entries are read (--stream)
+#[test]
+fn test_walk_order_and_streaming() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::create_dir_all(temp_dir.path().join("a/deep")).unwrap();
+    fs::create_dir(temp_dir.path().join("b")).unwrap();
+    fs::write(temp_dir.path().join("a/deep/file.txt"), "x").unwrap();
+    fs::write(temp_dir.path().join("b/other.txt"), "y").unwrap();
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-R").arg("--walk=bfs").arg(".").current_dir(temp_dir.path());
+
+    cmd.assert().success().stdout(predicate::eq(
+        "a\nb\n\n./a:\ndeep\n\n./b:\nother.txt\n\n./a/deep:\nfile.txt\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-R").arg("--walk=dfs").arg(".").current_dir(temp_dir.path());
+
+    cmd.assert().success().stdout(predicate::eq(
+        "a\nb\n\n./a:\ndeep\n\n./a/deep:\nfile.txt\n\n./b:\nother.txt\n",
+    ));
+
+    // Streamed entries come in directory read order, so only compare contents
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--flat").arg("--stream").current_dir(temp_dir.path());
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let mut lines: Vec<_> = String::from_utf8(output).unwrap().lines().map(String::from).collect();
+    lines.sort();
+    assert_eq!(lines, ["a", "a/deep", "a/deep/file.txt", "b", "b/other.txt"]);
+}
+
+/// Test hard link group numbers (--links) and per-inode path groups (--links=groups)
+#[test]
+fn test_hard_link_groups() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::create_dir(temp_dir.path().join("sub")).unwrap();
+    fs::write(temp_dir.path().join("a"), "a").unwrap();
+    fs::hard_link(temp_dir.path().join("a"), temp_dir.path().join("sub/b")).unwrap();
+    fs::write(temp_dir.path().join("c"), "c").unwrap();
+    fs::hard_link(temp_dir.path().join("c"), temp_dir.path().join("d")).unwrap();
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--links").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("-  a\n#1 c\n#1 d\n-  sub\n"));
+
+    // --zero keeps the group column instead of printing bare names
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.args(["--links", "--zero", "-1"]).arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("-  a\0#1 c\0#1 d\0-  sub\0"));
+
+    // Groups need the whole listing, which --stream never holds
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.args(["--links", "--stream"]).arg(temp_dir.path());
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with '--stream'"));
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--links=groups").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("inode "))
+        .stdout(predicate::str::contains(", 2 links:\na\nsub/b\n\ninode "))
+        .stdout(predicate::str::ends_with(", 2 links:\nc\nd\n"));
+
+    // Hidden links count as not listed, and --zero ends each group with an empty entry
+    fs::hard_link(temp_dir.path().join("c"), temp_dir.path().join(".e")).unwrap();
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--links=groups").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::ends_with(", 3 links (1 not listed):\nc\nd\n"));
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.args(["--links=groups", "--zero"]).arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("a\0sub/b\0\0c\0d\0\0"));
+}
+
+/// Test the duplicate file finder (--duplicates) in text and JSON
+#[test]
+fn test_duplicates() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::create_dir(temp_dir.path().join("sub")).unwrap();
+    fs::write(temp_dir.path().join("a.txt"), "same").unwrap();
+    fs::write(temp_dir.path().join("sub/b.txt"), "same").unwrap();
+    fs::write(temp_dir.path().join("c.txt"), "diff").unwrap();
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--duplicates").current_dir(temp_dir.path());
+
+    cmd.assert().success().stdout(predicate::eq(
+        "3 files examined, 1 redundant copy in 1 set, 4 reclaimable\n\
+         2 files of 4 (4 reclaimable):\n  ./a.txt\n  ./sub/b.txt\n",
+    ));
+
+    // Path headers follow --quoting-style like listing headers
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.args(["--duplicates", "--quoting-style=c", "sub", "sub"])
+        .current_dir(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("\"sub\":\n"));
+
+    // Other whole-run modes and listing options can't be combined with it
+    for args in [["--duplicates", "--report"], ["--duplicates", "--flat"], ["--duplicates", "-l"]] {
+        let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+        cmd.args(args).current_dir(temp_dir.path());
+
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--duplicates=json").current_dir(temp_dir.path());
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(json["reclaimable"], 4);
+    assert_eq!(json["sets"][0]["paths"], serde_json::json!(["./a.txt", "./sub/b.txt"]));
+}
@@ -178,4 +178,439 @@ fn test_multiple_paths_headers() {
         }
     }
     assert!(found_blank, "Should have blank line between multiple path listings");
-}
\ No newline at end of file
+}
+
+/// Test relative timestamps in long format (--time-style=relative)
+#[test]
+fn test_relative_time_style() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("fresh.txt"), "new").unwrap();
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-l").arg("--time-style=relative").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"just now\s+fresh\.txt").unwrap());
+}
+
+/// Test ISO timestamps in long format (--time-style=long-iso)
+#[test]
+fn test_long_iso_time_style() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("dated.txt"), "x").unwrap();
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-l").arg("--time-style=long-iso").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"\d{4}-\d{2}-\d{2} \d{2}:\d{2} dated\.txt").unwrap());
+}
+
+/// Test classification indicators (-F, -p, --file-type)
+#[test]
+#[cfg(unix)]
+fn test_classify_indicators() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::create_dir(temp_dir.path().join("dir")).unwrap();
+    let exec_file = temp_dir.path().join("run.sh");
+    File::create(&exec_file).unwrap();
+    fs::set_permissions(&exec_file, fs::Permissions::from_mode(0o755)).unwrap();
+    std::os::unix::fs::symlink("run.sh", temp_dir.path().join("link")).unwrap();
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-F").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"dir/\nlink@\nrun\.sh\*\n").unwrap());
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--file-type").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"dir/\nlink@\nrun\.sh\n").unwrap());
+
+    // -p after -F wins: only directories are marked
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-F").arg("-p").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"dir/\nlink\nrun\.sh\n").unwrap());
+}
+
+/// Test quoting styles for names with spaces and control characters
+#[test]
+fn test_quoting_styles() {
+    let temp_dir = TempDir::new().unwrap();
+    File::create(temp_dir.path().join("my file")).unwrap();
+    File::create(temp_dir.path().join("bad\nname")).unwrap();
+
+    // Piped output defaults to literal names
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("bad\nname\nmy file\n"));
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--quoting-style=shell-escape").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("'bad'$'\\n''name'\n'my file'\n"));
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-Q").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"bad\\nname\"\n\"my file\"\n"));
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-q").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("bad?name\n"));
+}
+
+/// Test comma-separated output (-m) wraps at the given width
+#[test]
+fn test_comma_format() {
+    let temp_dir = TempDir::new().unwrap();
+    for name in ["alpha", "beta", "gamma", "delta"] {
+        File::create(temp_dir.path().join(name)).unwrap();
+    }
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-m").arg("-w").arg("14").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("alpha, beta,\ndelta, gamma\n"));
+}
+
+/// Test column layouts (-C fills columns, --format=horizontal fills rows)
+#[test]
+fn test_grid_formats() {
+    let temp_dir = TempDir::new().unwrap();
+    for name in ["a1", "a2", "a3", "a4"] {
+        File::create(temp_dir.path().join(name)).unwrap();
+    }
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-C").arg("--width=10").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("a1  a3\na2  a4\n"));
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("--format=horizontal").arg("--width=10").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("a1  a2\na3  a4\n"));
+}
+
+/// Test that the last of -l and -1 wins
+#[test]
+fn test_last_format_flag_wins() {
+    let temp_dir = TempDir::new().unwrap();
+    File::create(temp_dir.path().join("file.txt")).unwrap();
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-1").arg("-l").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with("total"));
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-l").arg("-1").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("file.txt\n"));
+}
+
+/// Test that column layouts pad by display width, not bytes or chars
+#[test]
+fn test_grid_wide_characters() {
+    let temp_dir = TempDir::new().unwrap();
+    File::create(temp_dir.path().join("日本")).unwrap();
+    File::create(temp_dir.path().join("ab")).unwrap();
+    File::create(temp_dir.path().join("x")).unwrap();
+
+    // "日本" is 4 columns wide, so "ab" is padded to its width
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-x").arg("-w").arg("8").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("ab    x\n日本\n"));
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-C").arg("-w").arg("80").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("ab  x  日本\n"));
+}
+
+/// Test truncating long names in column formats (--max-name-width)
+#[test]
+fn test_max_name_width() {
+    let temp_dir = TempDir::new().unwrap();
+    File::create(temp_dir.path().join("a-very-long-file-name.txt")).unwrap();
+    File::create(temp_dir.path().join("short")).unwrap();
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-C").arg("--max-name-width=8").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("a-very-…  short\n"));
+
+    // 0 means no limit, as with --width
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-C").arg("--max-name-width=0").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("a-very-long-file-name.txt  short\n"));
+
+    // Single-column output is never truncated
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-1").arg("--max-name-width=8").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("a-very-long-file-name.txt"));
+}
+
+/// Sets a user extended attribute, returning false if the file system
+/// does not support them
+fn set_xattr(path: &std::path::Path, name: &str, value: &[u8]) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+    let name = CString::new(name).unwrap();
+    // SAFETY: both strings are NUL-terminated and `value` is valid for its length
+    let result = unsafe {
+        libc::setxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+            0,
+        )
+    };
+    result == 0
+}
+
+/// Whether a file already has an extended attribute, e.g. a label that
+/// the host's security module gives every new file
+fn has_xattr(path: &std::path::Path, name: &str) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+    let name = CString::new(name).unwrap();
+    // SAFETY: both strings are NUL-terminated, and a zero size only asks for the length
+    unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), std::ptr::null_mut(), 0) >= 0 }
+}
+
+/// Test the xattr marker in long format and the -@ attribute listing
+#[test]
+fn test_xattr_listing() {
+    let temp_dir = TempDir::new().unwrap();
+    let tagged = temp_dir.path().join("tagged.txt");
+    File::create(&tagged).unwrap();
+    File::create(temp_dir.path().join("plain.txt")).unwrap();
+    if !set_xattr(&tagged, "user.mime_type", b"text/plain") {
+        return;
+    }
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-l").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"(?m)^-[rw-]{9} +\d+ .*plain\.txt$").unwrap())
+        .stdout(predicate::str::is_match(r"(?m)^-[rw-]{9}@ +\d+ .*tagged\.txt$").unwrap());
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-1").arg("--xattr-preview").arg(&tagged);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\tuser.mime_type\t    10  \"text/plain\"\n"));
+
+    // Grid formats print one entry per line so the attributes follow their file
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-C").arg("-@").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("plain.txt\ntagged.txt\n\tuser.mime_type\t    10\n"));
+}
+
+/// Test the ACL marker in long format and the --acl entry listing
+#[test]
+fn test_acl_listing() {
+    let temp_dir = TempDir::new().unwrap();
+    let shared = temp_dir.path().join("shared.txt");
+    File::create(&shared).unwrap();
+
+    // Version 2 header, then (tag, perms, id) entries: owner rw-, uid 0 rwx,
+    // group r--, mask rwx, other r--
+    let mut value = 2u32.to_le_bytes().to_vec();
+    for (tag, perms, id) in [
+        (0x01u16, 6u16, u32::MAX),
+        (0x02, 7, 0),
+        (0x04, 4, u32::MAX),
+        (0x10, 7, u32::MAX),
+        (0x20, 4, u32::MAX),
+    ] {
+        value.extend(tag.to_le_bytes());
+        value.extend(perms.to_le_bytes());
+        value.extend(id.to_le_bytes());
+    }
+    if !set_xattr(&shared, "system.posix_acl_access", &value) {
+        return;
+    }
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-ln").arg("--acl").arg(&shared);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"^-rw-rwxr--\+ ").unwrap())
+        .stdout(predicate::str::contains(
+            "\tuser::rw-\n\tuser:0:rwx\n\tgroup::r--\n\tmask::rwx\n\tother::r--\n",
+        ));
+
+    // The default grid format lists the entries under their file too
+    File::create(temp_dir.path().join("plain.txt")).unwrap();
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-Cn").arg("--acl").arg(temp_dir.path());
+
+    cmd.assert().success().stdout(predicate::eq(
+        "plain.txt\nshared.txt\n\tuser::rw-\n\tuser:0:rwx\n\tgroup::r--\n\tmask::rwx\n\tother::r--\n",
+    ));
+}
+
+/// Test the security context column (-Z), with "?" for files without one
+#[test]
+fn test_security_context_column() {
+    let temp_dir = TempDir::new().unwrap();
+    let plain = temp_dir.path().join("plain");
+    File::create(&plain).unwrap();
+    // On SELinux hosts every new file is already labelled
+    let unlabeled = !has_xattr(&plain, "security.selinux");
+
+    if unlabeled {
+        let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+        cmd.arg("-1Z").arg(temp_dir.path());
+
+        cmd.assert()
+            .success()
+            .stdout(predicate::eq("? plain\n"));
+    }
+
+    // Setting a context needs privileges and a file system that stores it
+    let labeled = temp_dir.path().join("labeled");
+    File::create(&labeled).unwrap();
+    if !set_xattr(&labeled, "security.selinux", b"system_u:object_r:bin_t:s0\0") {
+        return;
+    }
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-1Z").arg(temp_dir.path());
+
+    let listing = cmd.assert().success();
+    if unlabeled {
+        listing.stdout(predicate::eq(
+            "system_u:object_r:bin_t:s0 labeled\n?                          plain\n",
+        ));
+    } else {
+        listing.stdout(predicate::str::is_match(r"^system_u:object_r:bin_t:s0 +labeled\n").unwrap());
+    }
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-lZ").arg(&labeled);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"^-[rw-]{9}\. .* system_u:object_r:bin_t:s0 +0 ").unwrap());
+}
+
+/// Test the file capabilities column (--caps)
+#[test]
+fn test_capabilities_column() {
+    let temp_dir = TempDir::new().unwrap();
+    let server = temp_dir.path().join("server");
+    File::create(&server).unwrap();
+    File::create(temp_dir.path().join("plain")).unwrap();
+
+    // Revision 2 data: effective flag, permitted cap_net_bind_service (bit 10)
+    let value: Vec<u8> = [0x0200_0001u32, 1 << 10, 0, 0, 0]
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .collect();
+    // Setting capabilities needs CAP_SETFCAP
+    if !set_xattr(&server, "security.capability", &value) {
+        return;
+    }
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-1").arg("--caps").arg(temp_dir.path());
+
+    cmd.assert().success().stdout(predicate::eq(
+        "-                       plain\ncap_net_bind_service=ep server\n",
+    ));
+
+    // Capability names are coloured from the `ca` key, which LS_COLORS overrides
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.env("CLICOLOR_FORCE", "1")
+        .env_remove("LS_COLORS")
+        .arg("-1")
+        .arg(&server);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[41;30m"));
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.env("CLICOLOR_FORCE", "1")
+        .env("LS_COLORS", "di=01;34:ca=01;32")
+        .arg("-1")
+        .arg(&server);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[1;32m"));
+}
+
+/// Test the inode flags column (--attrs), with dashes where flags can't be read
+#[test]
+fn test_inode_flags_column() {
+    let temp_dir = TempDir::new().unwrap();
+    File::create(temp_dir.path().join("file.txt")).unwrap();
+    std::os::unix::fs::symlink("file.txt", temp_dir.path().join("link")).unwrap();
+
+    let mut cmd = Command::cargo_bin("ls-rust").unwrap();
+    cmd.arg("-l").arg("--attrs").arg(temp_dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"(?m)^-\S+ +\d+ \S+ \S+ [-iadAcECxV]{9} +0 .*file\.txt$").unwrap())
+        .stdout(predicate::str::is_match(r"(?m)^l\S+ +\d+ \S+ \S+ --------- .*link -> file\.txt$").unwrap());
+}